@@ -0,0 +1,354 @@
+// Opening hours for single visitors or groups of them, on top of their action.
+//
+// An accepted visitor used to be let in at any hour. Now rules can say when they may come in:
+//
+//     allow = weekdays 15:00-19:00
+//     deny = fri 18:00-19:00
+//
+// A schedule is a set of days followed by an optional time window, local time (see clock.rs).
+// Days are `daily`, `weekdays`, `weekends`, or a comma separated list of days and ranges like
+// `mon,wed` or `mon-thu`. Without a window the rule covers the whole day. A window that ends
+// before it starts runs past midnight, so `fri,sat 22:00-02:00` covers early Saturday and Sunday too.
+//
+// Rules can be given to a visitor directly (see storage.rs) or to a group in the config file,
+// which visitors then join with `group = juniors`:
+//
+//     [group]
+//     name = juniors
+//     allow = weekdays 15:00-19:00
+//     allow = weekends 10:00-18:00
+//
+// A visitor's rules are their own plus those of every group they are in. A deny rule that fits the
+// time always wins. Otherwise, if there are any allow rules, one of them has to fit. A visitor
+// without rules is not limited at all.
+
+use std::fmt;
+
+use crate::clock::{Timestamp, Weekday, SECONDS_PER_DAY};
+use crate::Visitor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+impl Effect {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Effect::Allow => "allow",
+            Effect::Deny => "deny",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "allow" => Some(Effect::Allow),
+            "deny" => Some(Effect::Deny),
+            _ => None,
+        }
+    }
+}
+
+// Days of the week and a time window on each of them, in seconds since local midnight.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    days: [bool; 7], // indexed like Weekday::ALL, Monday first.
+    from: i64,
+    until: i64, // from == 0 and until == SECONDS_PER_DAY is the whole day.
+}
+
+fn day_index(day: Weekday) -> usize {
+    Weekday::ALL
+        .iter()
+        .position(|other| *other == day)
+        .expect("every weekday is in ALL")
+}
+
+// Parses HH:MM, where 24:00 is the end of the day.
+fn parse_time(text: &str) -> Option<i64> {
+    let (hours, minutes) = text.trim().split_once(':')?;
+    let hours: i64 = hours.parse().ok()?;
+    let minutes: i64 = minutes.parse().ok()?;
+    if minutes > 59 || hours > 24 || (hours == 24 && minutes > 0) || hours < 0 || minutes < 0 {
+        return None;
+    }
+    Some(hours * 3600 + minutes * 60)
+}
+
+fn parse_days(text: &str) -> Option<[bool; 7]> {
+    let mut days = [false; 7];
+    match text {
+        "daily" => return Some([true; 7]),
+        "weekdays" => return Some([true, true, true, true, true, false, false]),
+        "weekends" => return Some([false, false, false, false, false, true, true]),
+        _ => {}
+    }
+    for part in text.split(',') {
+        match part.trim().split_once('-') {
+            Some((first, last)) => {
+                let first = day_index(Weekday::from_label(first.trim())?);
+                let last = day_index(Weekday::from_label(last.trim())?);
+                // A range may wrap around the end of the week, like sat-mon.
+                let mut index = first;
+                loop {
+                    days[index] = true;
+                    if index == last {
+                        break;
+                    }
+                    index = (index + 1) % 7;
+                }
+            }
+            None => days[day_index(Weekday::from_label(part.trim())?)] = true,
+        }
+    }
+    Some(days)
+}
+
+impl Schedule {
+    // None when the text doesn't follow the format at the top of the file.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        let (days, window) = match text.split_once(char::is_whitespace) {
+            Some((days, window)) => (days, Some(window.trim())),
+            None => (text.as_str(), None),
+        };
+        let days = parse_days(days)?;
+        let (from, until) = match window {
+            // People write ranges with an en dash as often as with a hyphen.
+            Some(window) => {
+                let (from, until) = window.split_once(['-', '–'])?;
+                (parse_time(from)?, parse_time(until)?)
+            }
+            None => (0, SECONDS_PER_DAY),
+        };
+        if from == until || from == SECONDS_PER_DAY {
+            return None;
+        }
+        Some(Self { days, from, until })
+    }
+
+    fn on(&self, day: Weekday) -> bool {
+        self.days[day_index(day)]
+    }
+
+    // local is the time on the treehouse clock, see Timestamp::to_local.
+    pub fn contains(&self, local: Timestamp) -> bool {
+        let day = local.date().weekday();
+        let second = local.seconds_of_day();
+        if self.from < self.until {
+            return self.on(day) && (self.from..self.until).contains(&second);
+        }
+        // The window runs past midnight, so it may have started the day before.
+        let yesterday = Timestamp(local.0 - SECONDS_PER_DAY).date().weekday();
+        (self.on(day) && second >= self.from) || (self.on(yesterday) && second < self.until)
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.days {
+            [true, true, true, true, true, true, true] => write!(f, "daily")?,
+            [true, true, true, true, true, false, false] => write!(f, "weekdays")?,
+            [false, false, false, false, false, true, true] => write!(f, "weekends")?,
+            days => {
+                let names: Vec<&str> = Weekday::ALL
+                    .iter()
+                    .zip(days)
+                    .filter(|(_, on)| *on)
+                    .map(|(day, _)| &day.label()[..3])
+                    .collect();
+                write!(f, "{}", names.join(","))?
+            }
+        }
+        if (self.from, self.until) != (0, SECONDS_PER_DAY) {
+            let time = |seconds: i64| format!("{:02}:{:02}", seconds / 3600, seconds % 3600 / 60);
+            write!(f, " {}-{}", time(self.from), time(self.until))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessRule {
+    pub effect: Effect,
+    pub schedule: Schedule,
+}
+
+impl AccessRule {
+    // Parses the value of an allow or deny line.
+    pub fn parse(effect: Effect, text: &str) -> Result<Self, String> {
+        Schedule::parse(text)
+            .map(|schedule| Self { effect, schedule })
+            .ok_or_else(|| {
+                format!(
+                    "`{}` is not a schedule like `weekdays 15:00-19:00` or `sat,sun`",
+                    text
+                )
+            })
+    }
+}
+
+impl fmt::Display for AccessRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.effect.label(), self.schedule)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessGroup {
+    pub name: String,
+    pub rules: Vec<AccessRule>,
+}
+
+// Where a rule came from, so a decision can say so.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleSource {
+    Visitor,
+    Group(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourcedRule {
+    pub rule: AccessRule,
+    pub source: RuleSource,
+}
+
+impl fmt::Display for SourcedRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.source {
+            RuleSource::Visitor => write!(f, "{}", self.rule),
+            RuleSource::Group(group) => write!(f, "{} (group {})", self.rule, group),
+        }
+    }
+}
+
+// Why a visitor with rules may or may not come in at some time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessVerdict {
+    Allowed(SourcedRule),
+    Denied(SourcedRule),
+    // None of their allow rules fit, these are the ones they have.
+    OutsideHours(Vec<SourcedRule>),
+    // They are in a group the config doesn't have. Rather than guess, they are kept out.
+    UnknownGroup(String),
+}
+
+impl AccessVerdict {
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, AccessVerdict::Allowed(_))
+    }
+}
+
+// Every rule that applies to the visitor, their own first.
+pub fn rules_for(
+    visitor: &Visitor,
+    groups: &[AccessGroup],
+) -> Result<Vec<SourcedRule>, AccessVerdict> {
+    let mut rules: Vec<SourcedRule> = visitor
+        .access
+        .iter()
+        .map(|rule| SourcedRule {
+            rule: rule.clone(),
+            source: RuleSource::Visitor,
+        })
+        .collect();
+    for name in &visitor.groups {
+        let group = groups
+            .iter()
+            .find(|group| group.name == *name)
+            .ok_or_else(|| AccessVerdict::UnknownGroup(name.clone()))?;
+        rules.extend(group.rules.iter().map(|rule| SourcedRule {
+            rule: rule.clone(),
+            source: RuleSource::Group(group.name.clone()),
+        }));
+    }
+    Ok(rules)
+}
+
+// None when no rules apply to the visitor, so they are not limited.
+pub fn evaluate(
+    visitor: &Visitor,
+    groups: &[AccessGroup],
+    local: Timestamp,
+) -> Option<AccessVerdict> {
+    let rules = match rules_for(visitor, groups) {
+        Ok(rules) => rules,
+        Err(verdict) => return Some(verdict),
+    };
+    if rules.is_empty() {
+        return None;
+    }
+    let fits = |effect: Effect| {
+        rules
+            .iter()
+            .find(|sourced| sourced.rule.effect == effect && sourced.rule.schedule.contains(local))
+    };
+    if let Some(deny) = fits(Effect::Deny) {
+        return Some(AccessVerdict::Denied(deny.clone()));
+    }
+    if let Some(allow) = fits(Effect::Allow) {
+        return Some(AccessVerdict::Allowed(allow.clone()));
+    }
+    let allows: Vec<SourcedRule> = rules
+        .into_iter()
+        .filter(|sourced| sourced.rule.effect == Effect::Allow)
+        .collect();
+    if allows.is_empty() {
+        // Only deny rules, and none of them fit, so nothing stands in the way.
+        None
+    } else {
+        Some(AccessVerdict::OutsideHours(allows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2026-10-18 is a Sunday.
+    fn at(time: &str) -> Timestamp {
+        Timestamp::parse(time).expect("test times are valid")
+    }
+
+    #[test]
+    fn schedules_parse_and_print() {
+        for text in [
+            "daily",
+            "weekdays 15:00-19:00",
+            "fri,sat 22:00-02:00",
+            "mon,wed,sun",
+        ] {
+            assert_eq!(Schedule::parse(text).unwrap().to_string(), text);
+        }
+        assert_eq!(
+            Schedule::parse("Sat-Mon 10:00–12:00").unwrap().to_string(),
+            "mon,sat,sun 10:00-12:00"
+        );
+        for text in [
+            "someday",
+            "daily 10:00",
+            "daily 10:00-10:00",
+            "daily 25:00-26:00",
+        ] {
+            assert_eq!(Schedule::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn a_window_covers_its_days_and_hours() {
+        let weekends = Schedule::parse("weekends 10:00-18:00").unwrap();
+        assert!(weekends.contains(at("2026-10-18T10:00:00Z")));
+        assert!(!weekends.contains(at("2026-10-18T18:00:00Z")));
+        assert!(!weekends.contains(at("2026-10-19T12:00:00Z")));
+    }
+
+    #[test]
+    fn a_window_past_midnight_belongs_to_the_day_it_started() {
+        let late = Schedule::parse("sat 22:00-02:00").unwrap();
+        assert!(late.contains(at("2026-10-17T23:00:00Z")));
+        assert!(late.contains(at("2026-10-18T01:59:00Z")));
+        assert!(!late.contains(at("2026-10-18T22:30:00Z")));
+        assert!(!late.contains(at("2026-10-17T01:00:00Z")));
+    }
+}
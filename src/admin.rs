@@ -0,0 +1,1200 @@
+// Administrative commands for changing the visitor list without recompiling.
+//
+//     add <name> [--born <YYYY-MM-DD>] [--language <code>] [--action <action>] [--note <text>] [--greeting <text>] [--alias <alias>]...
+//     remove <name>
+//     add-alias <name> <alias>
+//     remove-alias <name> <alias>
+//     set-action <name> <action> [--note <text>]
+//     set-note <name> <text>        (an empty text removes the note)
+//     set-greeting <name> <text>    (a template, see template.rs)
+//     set-born <name> <YYYY-MM-DD>  (`unknown` forgets the birth date)
+//     set-language <name> <code>    (`default` goes back to the treehouse default, see messages.rs)
+//     add-rule <name> allow|deny <schedule>     (e.g. `weekdays 15:00-19:00`, see access.rs)
+//     remove-rule <name> allow|deny <schedule>
+//     add-group <name> <group>      (the group has to be in the config file)
+//     remove-group <name> <group>
+//     add-guardian <name> <guardian>    (see minors.rs)
+//     remove-guardian <name> <guardian>
+//     issue-pass <member> <guest> [--from <YYYY-MM-DD>] [--until <YYYY-MM-DD>] [--uses <n>]    (see passes.rs)
+//     approve <name> <member>       (for visitors on probation, see probation.rs)
+//     list
+//     show <name>
+//
+// Wherever a command takes a <name>, `#<id>` can be used instead to pick one of several visitors
+// who share a name. A plain name has to match exactly one visitor.
+//
+// Commands are parsed into an AdminCommand first and run second, so a typo is reported
+// before anything on disk is touched.
+
+use std::fmt;
+
+use crate::access::{AccessRule, Effect};
+use crate::clock::{Date, Timestamp};
+use crate::config::Config;
+use crate::messages::{is_valid_language, normalize_language};
+use crate::passes::GuestPass;
+use crate::probation::{self, Transition};
+use crate::sponsors;
+use crate::{RegistryError, Visitor, VisitorAction, VisitorId, VisitorRegistry};
+
+pub const ADMIN_COMMANDS: [&str; 19] = [
+    "add",
+    "remove",
+    "add-alias",
+    "remove-alias",
+    "set-action",
+    "set-note",
+    "set-greeting",
+    "set-born",
+    "set-language",
+    "add-rule",
+    "remove-rule",
+    "add-group",
+    "remove-group",
+    "add-guardian",
+    "remove-guardian",
+    "issue-pass",
+    "approve",
+    "list",
+    "show",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdminCommand {
+    Add {
+        name: String,
+        birth_date: Option<Date>,
+        language: Option<String>,
+        action: VisitorAction,
+        greeting: String,
+        aliases: Vec<String>,
+    },
+    Remove {
+        name: String,
+    },
+    AddAlias {
+        name: String,
+        alias: String,
+    },
+    RemoveAlias {
+        name: String,
+        alias: String,
+    },
+    SetAction {
+        name: String,
+        action: VisitorAction,
+    },
+    SetNote {
+        name: String,
+        note: String,
+    },
+    SetGreeting {
+        name: String,
+        greeting: String,
+    },
+    SetBirthDate {
+        name: String,
+        birth_date: Option<Date>,
+    },
+    SetLanguage {
+        name: String,
+        language: Option<String>,
+    },
+    AddRule {
+        name: String,
+        rule: AccessRule,
+    },
+    RemoveRule {
+        name: String,
+        rule: AccessRule,
+    },
+    AddGroup {
+        name: String,
+        group: String,
+    },
+    RemoveGroup {
+        name: String,
+        group: String,
+    },
+    AddGuardian {
+        name: String,
+        guardian: String,
+    },
+    RemoveGuardian {
+        name: String,
+        guardian: String,
+    },
+    // The dates are local and both included, see GuestPass::for_days.
+    IssuePass {
+        member: String,
+        guest: String,
+        from: Date,
+        until: Date,
+        max_uses: Option<u32>,
+    },
+    Approve {
+        name: String,
+        member: String,
+    },
+    List,
+    Show {
+        name: String,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AdminError {
+    Usage(String),
+    Invalid(String),
+    Registry(RegistryError),
+}
+
+impl fmt::Display for AdminError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AdminError::Usage(message) => write!(f, "usage: {}", message),
+            AdminError::Invalid(message) => write!(f, "{}", message),
+            AdminError::Registry(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for AdminError {}
+
+impl From<RegistryError> for AdminError {
+    fn from(error: RegistryError) -> Self {
+        AdminError::Registry(error)
+    }
+}
+
+// What running a command did. changed tells the caller whether the registry needs saving, and probation
+// which visitor's probation started or ended, for the audit log (see probation.rs).
+#[derive(Debug, PartialEq)]
+pub struct AdminReport {
+    pub changed: bool,
+    pub lines: Vec<String>,
+    pub probation: Option<(VisitorId, Transition)>,
+}
+
+impl AdminReport {
+    fn changed(line: String) -> Self {
+        Self {
+            changed: true,
+            lines: vec![line],
+            probation: None,
+        }
+    }
+
+    fn unchanged(lines: Vec<String>) -> Self {
+        Self {
+            changed: false,
+            lines,
+            probation: None,
+        }
+    }
+}
+
+// A birth date as YYYY-MM-DD, or `unknown` for None. Dates after today are typos and rejected.
+pub fn parse_birth_date(text: &str, today: Date) -> Result<Option<Date>, AdminError> {
+    if text == "unknown" {
+        return Ok(None);
+    }
+    match Date::parse(text) {
+        Some(date) if date <= today => Ok(Some(date)),
+        Some(date) => Err(invalid(format!("birth date {} is in the future", date))),
+        None => Err(invalid(format!(
+            "birth date `{}` must be a date like 2011-06-30, or unknown",
+            text
+        ))),
+    }
+}
+
+// A language code like en or es, or `default` for None.
+pub fn parse_language(text: &str) -> Result<Option<String>, AdminError> {
+    if text == "default" {
+        return Ok(None);
+    }
+    if !is_valid_language(text) {
+        return Err(invalid(format!(
+            "language `{}` must be a code like en or es, or default",
+            text
+        )));
+    }
+    Ok(Some(normalize_language(text)))
+}
+
+// allow or deny followed by a schedule. The schedule may be one argument or several,
+// so `add-rule Steve allow weekdays 15:00-19:00` works without quotes.
+fn parse_rule(effect: &str, schedule: &[String]) -> Result<AccessRule, AdminError> {
+    let effect = Effect::from_label(effect)
+        .ok_or_else(|| invalid(format!("expected allow or deny, not `{}`", effect)))?;
+    AccessRule::parse(effect, &schedule.join(" ")).map_err(invalid)
+}
+
+// The plain words and `--flag value` pairs found in a command's arguments.
+struct SplitArgs<'a> {
+    words: Vec<&'a str>,
+    flags: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> SplitArgs<'a> {
+    fn new(args: &'a [String], allowed: &[&str]) -> Result<Self, AdminError> {
+        let mut split = Self {
+            words: Vec::new(),
+            flags: Vec::new(),
+        };
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if let Some(flag) = arg.strip_prefix("--") {
+                if !allowed.contains(&flag) {
+                    return Err(invalid(format!("unknown option --{}", flag)));
+                }
+                let value = iter
+                    .next()
+                    .ok_or_else(|| invalid(format!("--{} needs a value", flag)))?;
+                split.flags.push((flag, value.as_str()));
+            } else {
+                split.words.push(arg.as_str());
+            }
+        }
+        Ok(split)
+    }
+
+    fn flag(&self, name: &str) -> Option<&'a str> {
+        self.all(name).next()
+    }
+
+    // For flags that may be given more than once, like --alias.
+    fn all<'s>(&'s self, name: &'s str) -> impl Iterator<Item = &'a str> + 's {
+        self.flags
+            .iter()
+            .filter(move |(flag, _)| *flag == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+fn invalid(message: String) -> AdminError {
+    AdminError::Invalid(message)
+}
+
+// today is only used to reject birth dates in the future.
+pub fn parse_command(
+    command: &str,
+    args: &[String],
+    today: Date,
+) -> Result<AdminCommand, AdminError> {
+    let usage = |text: &str| AdminError::Usage(format!("{} {}", command, text));
+
+    match command {
+        "add" => {
+            let split = SplitArgs::new(
+                args,
+                &["born", "language", "action", "note", "greeting", "alias"],
+            )?;
+            let [name] = split.words.as_slice() else {
+                return Err(usage(
+                    "<name> [--born <YYYY-MM-DD>] [--language <code>] [--action <action>] [--note <text>] [--greeting <text>] [--alias <alias>]...",
+                ));
+            };
+            let language = match split.flag("language") {
+                Some(language) => parse_language(language)?,
+                None => None,
+            };
+            let birth_date = match split.flag("born") {
+                Some(born) => parse_birth_date(born, today)?,
+                None => None,
+            };
+            let action = VisitorAction::from_label(
+                split.flag("action").unwrap_or("accept"),
+                split.flag("note"),
+            )
+            .map_err(invalid)?;
+            Ok(AdminCommand::Add {
+                name: name.to_string(),
+                birth_date,
+                language,
+                action,
+                greeting: split.flag("greeting").unwrap_or("New friend").to_string(),
+                aliases: split.all("alias").map(str::to_string).collect(),
+            })
+        }
+        "remove" => match args {
+            [name] => Ok(AdminCommand::Remove { name: name.clone() }),
+            _ => Err(usage("<name>")),
+        },
+        "add-alias" => match args {
+            [name, alias] => Ok(AdminCommand::AddAlias {
+                name: name.clone(),
+                alias: alias.clone(),
+            }),
+            _ => Err(usage("<name> <alias>")),
+        },
+        "remove-alias" => match args {
+            [name, alias] => Ok(AdminCommand::RemoveAlias {
+                name: name.clone(),
+                alias: alias.clone(),
+            }),
+            _ => Err(usage("<name> <alias>")),
+        },
+        "set-action" => {
+            let split = SplitArgs::new(args, &["note"])?;
+            let [name, label] = split.words.as_slice() else {
+                return Err(usage("<name> <action> [--note <text>]"));
+            };
+            let action = VisitorAction::from_label(label, split.flag("note")).map_err(invalid)?;
+            Ok(AdminCommand::SetAction {
+                name: name.to_string(),
+                action,
+            })
+        }
+        "set-note" => match args {
+            [name, note] => Ok(AdminCommand::SetNote {
+                name: name.clone(),
+                note: note.clone(),
+            }),
+            _ => Err(usage("<name> <text>")),
+        },
+        "set-greeting" => match args {
+            [name, greeting] => Ok(AdminCommand::SetGreeting {
+                name: name.clone(),
+                greeting: greeting.clone(),
+            }),
+            _ => Err(usage("<name> <text>")),
+        },
+        "set-born" => match args {
+            [name, born] => Ok(AdminCommand::SetBirthDate {
+                name: name.clone(),
+                birth_date: parse_birth_date(born, today)?,
+            }),
+            _ => Err(usage("<name> <YYYY-MM-DD>")),
+        },
+        "set-language" => match args {
+            [name, language] => Ok(AdminCommand::SetLanguage {
+                name: name.clone(),
+                language: parse_language(language)?,
+            }),
+            _ => Err(usage("<name> <code>")),
+        },
+        "add-rule" | "remove-rule" => match args {
+            [name, effect, schedule @ ..] if !schedule.is_empty() => {
+                let name = name.clone();
+                let rule = parse_rule(effect, schedule)?;
+                Ok(if command == "add-rule" {
+                    AdminCommand::AddRule { name, rule }
+                } else {
+                    AdminCommand::RemoveRule { name, rule }
+                })
+            }
+            _ => Err(usage("<name> allow|deny <schedule>")),
+        },
+        "add-group" => match args {
+            [name, group] => Ok(AdminCommand::AddGroup {
+                name: name.clone(),
+                group: group.clone(),
+            }),
+            _ => Err(usage("<name> <group>")),
+        },
+        "remove-group" => match args {
+            [name, group] => Ok(AdminCommand::RemoveGroup {
+                name: name.clone(),
+                group: group.clone(),
+            }),
+            _ => Err(usage("<name> <group>")),
+        },
+        "add-guardian" => match args {
+            [name, guardian] => Ok(AdminCommand::AddGuardian {
+                name: name.clone(),
+                guardian: guardian.clone(),
+            }),
+            _ => Err(usage("<name> <guardian>")),
+        },
+        "remove-guardian" => match args {
+            [name, guardian] => Ok(AdminCommand::RemoveGuardian {
+                name: name.clone(),
+                guardian: guardian.clone(),
+            }),
+            _ => Err(usage("<name> <guardian>")),
+        },
+        "issue-pass" => {
+            let split = SplitArgs::new(args, &["from", "until", "uses"])?;
+            let [member, guest] = split.words.as_slice() else {
+                return Err(usage(
+                    "<member> <guest> [--from <YYYY-MM-DD>] [--until <YYYY-MM-DD>] [--uses <n>]",
+                ));
+            };
+            let date = |flag: &str| match split.flag(flag) {
+                Some(text) => Date::parse(text).map(Some).ok_or_else(|| {
+                    invalid(format!(
+                        "--{} `{}` must be a date like 2026-10-24",
+                        flag, text
+                    ))
+                }),
+                None => Ok(None),
+            };
+            // A pass without dates is for today only.
+            let from = date("from")?.unwrap_or(today);
+            let until = date("until")?.unwrap_or(from);
+            if until < from {
+                return Err(invalid(format!(
+                    "the pass would end on {}, before it starts on {}",
+                    until, from
+                )));
+            }
+            let max_uses = match split.flag("uses") {
+                Some(text) => match text.parse::<u32>() {
+                    Ok(uses) if uses > 0 => Some(uses),
+                    _ => {
+                        return Err(invalid(format!(
+                            "--uses `{}` must be a whole number above 0",
+                            text
+                        )))
+                    }
+                },
+                None => None,
+            };
+            Ok(AdminCommand::IssuePass {
+                member: member.to_string(),
+                guest: guest.to_string(),
+                from,
+                until,
+                max_uses,
+            })
+        }
+        "approve" => match args {
+            [name, member] => Ok(AdminCommand::Approve {
+                name: name.clone(),
+                member: member.clone(),
+            }),
+            _ => Err(usage("<name> <member>")),
+        },
+        "list" => match args {
+            [] => Ok(AdminCommand::List),
+            _ => Err(usage("")),
+        },
+        "show" => match args {
+            [name] => Ok(AdminCommand::Show { name: name.clone() }),
+            _ => Err(usage("<name>")),
+        },
+        other => Err(invalid(format!("unknown command `{}`", other))),
+    }
+}
+
+fn describe_action(action: &VisitorAction) -> String {
+    match action {
+        VisitorAction::AcceptWithNote { note } => format!("{} ({})", action.label(), note),
+        _ => action.label().to_string(),
+    }
+}
+
+// "?" when the birth date isn't known.
+fn describe_age(visitor: &Visitor, today: Date) -> String {
+    visitor
+        .age_on(today)
+        .map_or("?".to_string(), |age| age.to_string())
+}
+
+fn describe_birth_date(birth_date: Option<Date>) -> String {
+    birth_date.map_or("unknown".to_string(), |date| date.to_string())
+}
+
+fn describe_language(language: &Option<String>) -> &str {
+    language.as_deref().unwrap_or("default")
+}
+
+fn describe_pass(pass: &GuestPass) -> String {
+    let uses = pass
+        .max_uses
+        .map_or(String::new(), |uses| format!(", uses: {}", uses));
+    format!(
+        "from #{}, {} to {}{}",
+        pass.issued_by, pass.valid_from, pass.valid_until, uses
+    )
+}
+
+fn summary_line(visitor: &Visitor, today: Date) -> String {
+    let guest = if visitor.pass.is_some() {
+        " (guest)"
+    } else {
+        ""
+    };
+    format!(
+        "{:>4}  {:<16} {:>3}  {}{}",
+        visitor.id,
+        visitor.name,
+        describe_age(visitor, today),
+        describe_action(&visitor.action),
+        guest
+    )
+}
+
+// Admin commands only know the date, so probation set by one starts at the beginning of the local day.
+fn start_of(today: Date, config: &Config) -> Timestamp {
+    Timestamp(today.start().0 - config.utc_offset.0)
+}
+
+fn tag(visitor: &Visitor) -> String {
+    format!("{} (#{})", visitor.name, visitor.id)
+}
+
+// Finds the one visitor an admin meant, by name or by #id.
+fn selected<'a>(
+    registry: &'a mut VisitorRegistry,
+    selector: &str,
+) -> Result<&'a mut Visitor, AdminError> {
+    let id = registry.resolve(selector)?;
+    Ok(registry
+        .get_mut(id)
+        .expect("resolve only returns ids that exist"))
+}
+
+// today is used to work out ages from birth dates. The config has the groups and the age of a minor.
+pub fn run_command(
+    registry: &mut VisitorRegistry,
+    command: AdminCommand,
+    today: Date,
+    config: &Config,
+) -> Result<AdminReport, AdminError> {
+    match command {
+        AdminCommand::Add {
+            name,
+            birth_date,
+            language,
+            action,
+            greeting,
+            aliases,
+        } => {
+            let others = registry.find_by_name(&name).len();
+            let mut visitor = Visitor::new(&name, &greeting, action, birth_date);
+            if visitor.action == VisitorAction::Probation {
+                visitor.probation_since = Some(start_of(today, config));
+            }
+            visitor.aliases = aliases;
+            visitor.language = language;
+            let id = registry.add(visitor)?;
+            let visitor = registry.get(id).expect("visitor was just added");
+            let mut report = AdminReport::changed(format!(
+                "added {}, age {}, {}",
+                tag(visitor),
+                describe_age(visitor, today),
+                describe_action(&visitor.action)
+            ));
+            if visitor.action == VisitorAction::Probation {
+                report.probation = Some((id, Transition::Started));
+            }
+            if others > 0 {
+                report.lines.push(format!(
+                    "there are now {} visitors called {}, use #{} to pick this one",
+                    others + 1,
+                    visitor.name,
+                    id
+                ));
+            }
+            Ok(report)
+        }
+        AdminCommand::Remove { name } => {
+            let id = registry.resolve(&name)?;
+            let visitor = registry.remove(id)?;
+            Ok(AdminReport::changed(format!("removed {}", tag(&visitor))))
+        }
+        AdminCommand::AddAlias { name, alias } => {
+            let id = registry.resolve(&name)?;
+            registry.add_alias(id, &alias)?;
+            let visitor = registry
+                .get(id)
+                .expect("resolve only returns ids that exist");
+            Ok(AdminReport::changed(format!(
+                "{}: aliases are now {}",
+                tag(visitor),
+                visitor.aliases.join(", ")
+            )))
+        }
+        AdminCommand::RemoveAlias { name, alias } => {
+            let id = registry.resolve(&name)?;
+            let removed = registry.remove_alias(id, &alias)?;
+            let visitor = registry
+                .get(id)
+                .expect("resolve only returns ids that exist");
+            Ok(AdminReport::changed(format!(
+                "{}: removed alias {}",
+                tag(visitor),
+                removed
+            )))
+        }
+        AdminCommand::SetAction { name, action } => {
+            let id = registry.resolve(&name)?;
+            let new = describe_action(&action);
+            let old = registry.update_action(id, action)?;
+            let visitor = registry
+                .get_mut(id)
+                .expect("resolve only returns ids that exist");
+            // Going on probation starts it afresh, leaving it forgets it, see probation.rs.
+            let transition = match (&old, &visitor.action) {
+                (VisitorAction::Probation, VisitorAction::Probation) => None,
+                (_, VisitorAction::Probation) => Some(Transition::Started),
+                (VisitorAction::Probation, _) => Some(Transition::Ended),
+                _ => None,
+            };
+            if let Some(transition) = transition {
+                probation::apply(visitor, transition, start_of(today, config));
+            }
+            let mut report = AdminReport::changed(format!(
+                "{}: action {} -> {}",
+                tag(visitor),
+                describe_action(&old),
+                new
+            ));
+            report.probation = transition.map(|transition| (id, transition));
+            Ok(report)
+        }
+        AdminCommand::SetNote { name, note } => {
+            let visitor = selected(registry, &name)?;
+            let old = describe_action(&visitor.action);
+            // Notes only make sense for visitors who are let in, so refused or probationary visitors keep their action.
+            visitor.action = match (&visitor.action, note.is_empty()) {
+                (VisitorAction::Accept | VisitorAction::AcceptWithNote { .. }, true) => {
+                    VisitorAction::Accept
+                }
+                (VisitorAction::Accept | VisitorAction::AcceptWithNote { .. }, false) => {
+                    VisitorAction::AcceptWithNote { note }
+                }
+                (other, _) => {
+                    return Err(invalid(format!(
+                        "{} is {}, notes can only be set on accepted visitors",
+                        tag(visitor),
+                        other.label()
+                    )))
+                }
+            };
+            Ok(AdminReport::changed(format!(
+                "{}: action {} -> {}",
+                tag(visitor),
+                old,
+                describe_action(&visitor.action)
+            )))
+        }
+        AdminCommand::SetGreeting { name, greeting } => {
+            let id = registry.resolve(&name)?;
+            let old = registry.update_greeting(id, &greeting)?;
+            let visitor = registry
+                .get(id)
+                .expect("resolve only returns ids that exist");
+            Ok(AdminReport::changed(format!(
+                "{}: greeting \"{}\" -> \"{}\"",
+                tag(visitor),
+                old,
+                visitor.greeting
+            )))
+        }
+        AdminCommand::SetBirthDate { name, birth_date } => {
+            let visitor = selected(registry, &name)?;
+            let old = visitor.birth_date;
+            visitor.birth_date = birth_date;
+            Ok(AdminReport::changed(format!(
+                "{}: born {} -> {}",
+                tag(visitor),
+                describe_birth_date(old),
+                describe_birth_date(birth_date)
+            )))
+        }
+        AdminCommand::SetLanguage { name, language } => {
+            let visitor = selected(registry, &name)?;
+            let old = std::mem::replace(&mut visitor.language, language);
+            Ok(AdminReport::changed(format!(
+                "{}: language {} -> {}",
+                tag(visitor),
+                describe_language(&old),
+                describe_language(&visitor.language)
+            )))
+        }
+        AdminCommand::AddRule { name, rule } => {
+            let visitor = selected(registry, &name)?;
+            if visitor.access.contains(&rule) {
+                return Err(invalid(format!("{} already has {}", tag(visitor), rule)));
+            }
+            let line = format!("{}: added {}", tag(visitor), rule);
+            visitor.access.push(rule);
+            Ok(AdminReport::changed(line))
+        }
+        AdminCommand::RemoveRule { name, rule } => {
+            let visitor = selected(registry, &name)?;
+            let Some(index) = visitor.access.iter().position(|other| *other == rule) else {
+                return Err(invalid(format!("{} has no rule {}", tag(visitor), rule)));
+            };
+            visitor.access.remove(index);
+            Ok(AdminReport::changed(format!(
+                "{}: removed {}",
+                tag(visitor),
+                rule
+            )))
+        }
+        AdminCommand::AddGroup { name, group } => {
+            let visitor = selected(registry, &name)?;
+            // A misspelt group would keep the visitor out, see access::AccessVerdict::UnknownGroup.
+            if !config.groups.iter().any(|known| known.name == group) {
+                return Err(invalid(format!(
+                    "there is no group called {} in the config file",
+                    group
+                )));
+            }
+            if visitor.groups.contains(&group) {
+                return Err(invalid(format!("{} is already in {}", tag(visitor), group)));
+            }
+            visitor.groups.push(group);
+            Ok(AdminReport::changed(format!(
+                "{}: groups are now {}",
+                tag(visitor),
+                visitor.groups.join(", ")
+            )))
+        }
+        AdminCommand::RemoveGroup { name, group } => {
+            let visitor = selected(registry, &name)?;
+            let Some(index) = visitor.groups.iter().position(|other| *other == group) else {
+                return Err(invalid(format!("{} is not in {}", tag(visitor), group)));
+            };
+            visitor.groups.remove(index);
+            Ok(AdminReport::changed(format!(
+                "{}: left {}",
+                tag(visitor),
+                group
+            )))
+        }
+        AdminCommand::AddGuardian { name, guardian } => {
+            let id = registry.resolve(&name)?;
+            let guardian_id = registry.resolve(&guardian)?;
+            let guardian = registry
+                .get(guardian_id)
+                .expect("resolve only returns ids that exist");
+            if guardian_id == id {
+                return Err(invalid(format!(
+                    "{} can't be their own guardian",
+                    tag(guardian)
+                )));
+            }
+            // A guardian whose age isn't known is allowed, the door can't check it either way.
+            if let Some(age) = guardian.age_on(today) {
+                if age < u32::from(config.minors.age) {
+                    return Err(invalid(format!(
+                        "{} is {}, a guardian has to be at least {}",
+                        tag(guardian),
+                        age,
+                        config.minors.age
+                    )));
+                }
+            }
+            let guardian = tag(guardian);
+            let visitor = selected(registry, &name)?;
+            if visitor.guardians.contains(&guardian_id) {
+                return Err(invalid(format!(
+                    "{} is already a guardian of {}",
+                    guardian,
+                    tag(visitor)
+                )));
+            }
+            visitor.guardians.push(guardian_id);
+            Ok(AdminReport::changed(format!(
+                "{}: added guardian {}",
+                tag(visitor),
+                guardian
+            )))
+        }
+        AdminCommand::RemoveGuardian { name, guardian } => {
+            let guardian_id = registry.resolve(&guardian)?;
+            let visitor = selected(registry, &name)?;
+            let Some(index) = visitor
+                .guardians
+                .iter()
+                .position(|other| *other == guardian_id)
+            else {
+                return Err(invalid(format!(
+                    "#{} is not a guardian of {}",
+                    guardian_id,
+                    tag(visitor)
+                )));
+            };
+            visitor.guardians.remove(index);
+            Ok(AdminReport::changed(format!(
+                "{}: removed guardian #{}",
+                tag(visitor),
+                guardian_id
+            )))
+        }
+        AdminCommand::IssuePass {
+            member,
+            guest,
+            from,
+            until,
+            max_uses,
+        } => {
+            let member_id = registry.resolve(&member)?;
+            let member = registry
+                .get(member_id)
+                .expect("resolve only returns ids that exist");
+            // Only members in good standing can bring somebody along, and guests can't pass theirs on.
+            if !matches!(
+                member.action,
+                VisitorAction::Accept | VisitorAction::AcceptWithNote { .. }
+            ) {
+                return Err(invalid(format!(
+                    "{} is {}, only accepted members can issue passes",
+                    tag(member),
+                    member.action.label()
+                )));
+            }
+            if member.pass.is_some() {
+                return Err(invalid(format!(
+                    "{} is a guest, guests can't issue passes",
+                    tag(member)
+                )));
+            }
+            let pass = GuestPass::for_days(member_id, from, until, max_uses, config.utc_offset);
+            let (id, added) = match registry.resolve(&guest) {
+                Ok(id) => (id, false),
+                Err(RegistryError::NotFound(_)) => {
+                    let visitor = Visitor::new(&guest, "New friend", VisitorAction::Accept, None);
+                    (registry.add(visitor)?, true)
+                }
+                Err(error) => return Err(error.into()),
+            };
+            let visitor = registry
+                .get_mut(id)
+                .expect("resolve only returns ids that exist");
+            // Somebody already on the list for good doesn't need a pass, and giving them one would limit them.
+            if !added && visitor.pass.is_none() {
+                return Err(invalid(format!(
+                    "{} is already on the visitor list",
+                    tag(visitor)
+                )));
+            }
+            let renewed = visitor.pass.replace(pass).is_some();
+            Ok(AdminReport::changed(format!(
+                "{}: {} pass {}",
+                tag(visitor),
+                if renewed { "renewed" } else { "issued" },
+                describe_pass(&pass)
+            )))
+        }
+        AdminCommand::Approve { name, member } => {
+            let id = registry.resolve(&name)?;
+            let member_id = registry.resolve(&member)?;
+            let member = registry
+                .get(member_id)
+                .expect("resolve only returns ids that exist");
+            if member_id == id {
+                return Err(invalid(format!("{} can't approve themselves", tag(member))));
+            }
+            // Only members who could vouch for somebody new can approve somebody on probation.
+            if let Err(refusal) = sponsors::can_vouch(member, &config.minors, today.start()) {
+                return Err(invalid(format!(
+                    "{} can't approve anybody, {}",
+                    tag(member),
+                    refusal
+                )));
+            }
+            let member = tag(member);
+            let visitor = selected(registry, &name)?;
+            if visitor.action != VisitorAction::Probation {
+                return Err(invalid(format!(
+                    "{} is {}, only visitors on probation can be approved",
+                    tag(visitor),
+                    visitor.action.label()
+                )));
+            }
+            if visitor.approved_by.contains(&member_id) {
+                return Err(invalid(format!(
+                    "{} already approved {}",
+                    member,
+                    tag(visitor)
+                )));
+            }
+            visitor.approved_by.push(member_id);
+            Ok(AdminReport::changed(format!(
+                "{}: approved by {}, {} approval(s) so far",
+                tag(visitor),
+                member,
+                visitor.approved_by.len()
+            )))
+        }
+        AdminCommand::List => {
+            let mut lines = vec![format!(
+                "{:>4}  {:<16} {:>3}  {}",
+                "ID", "NAME", "AGE", "ACTION"
+            )];
+            lines.extend(registry.iter().map(|visitor| summary_line(visitor, today)));
+            Ok(AdminReport::unchanged(lines))
+        }
+        AdminCommand::Show { name } => {
+            let id = registry.resolve(&name)?;
+            // A sponsor may have been removed since, their id is never handed out again.
+            let sponsor = registry
+                .get(id)
+                .and_then(|visitor| visitor.sponsor)
+                .map(|sponsor| match registry.get(sponsor) {
+                    Some(sponsor) => tag(sponsor),
+                    None => format!("#{} (removed)", sponsor),
+                });
+            let visitor = selected(registry, &name)?;
+            let mut lines = vec![
+                format!("id:       {}", visitor.id),
+                format!("name:     {}", visitor.name),
+                format!("born:     {}", describe_birth_date(visitor.birth_date)),
+                format!("age:      {}", describe_age(visitor, today)),
+                format!("language: {}", describe_language(&visitor.language)),
+                format!("action:   {}", visitor.action.label()),
+            ];
+            if let VisitorAction::AcceptWithNote { note } = &visitor.action {
+                lines.push(format!("note:     {}", note));
+            }
+            lines.push(format!("greeting: {}", visitor.greeting));
+            if !visitor.aliases.is_empty() {
+                lines.push(format!("aliases:  {}", visitor.aliases.join(", ")));
+            }
+            if !visitor.groups.is_empty() {
+                lines.push(format!("groups:   {}", visitor.groups.join(", ")));
+            }
+            for rule in &visitor.access {
+                lines.push(format!("rule:     {}", rule));
+            }
+            if !visitor.guardians.is_empty() {
+                let guardians: Vec<String> = visitor
+                    .guardians
+                    .iter()
+                    .map(|id| format!("#{}", id))
+                    .collect();
+                lines.push(format!("guardians: {}", guardians.join(", ")));
+            }
+            if let Some(pass) = &visitor.pass {
+                lines.push(format!("pass:     {}", describe_pass(pass)));
+            }
+            if let Some(sponsor) = sponsor {
+                lines.push(format!("sponsor:  {}", sponsor));
+            }
+            if let Some(since) = visitor.probation_since {
+                lines.push(format!("probation since: {}", since));
+            }
+            if !visitor.approved_by.is_empty() {
+                let members: Vec<String> = visitor
+                    .approved_by
+                    .iter()
+                    .map(|id| format!("#{}", id))
+                    .collect();
+                lines.push(format!("approved by: {}", members.join(", ")));
+            }
+            Ok(AdminReport::unchanged(lines))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> Date {
+        Date::new(2026, 10, 18).unwrap()
+    }
+
+    fn registry() -> VisitorRegistry {
+        let mut registry = VisitorRegistry::new();
+        for name in ["Bert", "Steve", "Steve"] {
+            let visitor = Visitor::new(name, "Hi", VisitorAction::Accept, Date::new(1980, 1, 1));
+            registry.add(visitor).unwrap();
+        }
+        registry
+    }
+
+    // Parses and runs a command line like the binary does, with the default config.
+    fn run(registry: &mut VisitorRegistry, line: &[&str]) -> Result<AdminReport, AdminError> {
+        let args: Vec<String> = line[1..].iter().map(|arg| arg.to_string()).collect();
+        let command = parse_command(line[0], &args, today())?;
+        run_command(registry, command, today(), &Config::default())
+    }
+
+    #[test]
+    fn adding_a_visitor() {
+        let mut registry = registry();
+        let report = run(
+            &mut registry,
+            &[
+                "add",
+                "May",
+                "--born",
+                "1960-03-01",
+                "--language",
+                "ES",
+                "--alias",
+                "Maisie",
+                "--action",
+                "probation",
+            ],
+        )
+        .unwrap();
+        assert!(report.changed);
+        assert_eq!(report.lines, ["added May (#4), age 66, probation"]);
+        assert_eq!(report.probation, Some((4, Transition::Started)));
+
+        let may = registry.get(4).unwrap();
+        assert_eq!(may.aliases, ["Maisie"]);
+        assert_eq!(may.language.as_deref(), Some("es"));
+        assert_eq!(may.greeting, "New friend");
+        assert_eq!(may.probation_since, Some(today().start()));
+
+        let report = run(&mut registry, &["add", "Bert"]).unwrap();
+        assert_eq!(report.lines.len(), 2);
+        assert!(report.lines[1].contains("use #5"));
+        assert_eq!(registry.get(5).unwrap().birth_date, None);
+    }
+
+    #[test]
+    fn adding_rejects_bad_input() {
+        let mut registry = registry();
+        let mut error = |line: &[&str]| run(&mut registry, line).unwrap_err();
+        assert!(matches!(error(&["add"]), AdminError::Usage(_)));
+        assert!(matches!(
+            error(&["add", "May", "June"]),
+            AdminError::Usage(_)
+        ));
+        assert!(matches!(
+            error(&["add", "May", "--colour", "red"]),
+            AdminError::Invalid(_)
+        ));
+        assert!(matches!(
+            error(&["add", "May", "--born"]),
+            AdminError::Invalid(_)
+        ));
+        assert!(matches!(
+            error(&["add", "May", "--born", "2030-01-01"]),
+            AdminError::Invalid(_)
+        ));
+        assert!(matches!(
+            error(&["add", "May", "--language", "e5"]),
+            AdminError::Invalid(_)
+        ));
+        assert!(matches!(
+            error(&["add", "May", "--note", "hi"]),
+            AdminError::Invalid(_)
+        ));
+        assert!(matches!(
+            error(&["add", "May", "--action", "maybe"]),
+            AdminError::Invalid(_)
+        ));
+        assert_eq!(
+            error(&["add", "May", "--alias", "bert"]),
+            AdminError::Registry(RegistryError::AliasTaken {
+                alias: "bert".to_string(),
+                owner: 1
+            })
+        );
+        assert_eq!(registry.len(), 3);
+    }
+
+    #[test]
+    fn removing_a_visitor() {
+        let mut registry = registry();
+        let report = run(&mut registry, &["remove", "Bert"]).unwrap();
+        assert_eq!(report.lines, ["removed Bert (#1)"]);
+        assert_eq!(
+            run(&mut registry, &["remove", "Bert"]),
+            Err(AdminError::Registry(RegistryError::NotFound(
+                "Bert".to_string()
+            )))
+        );
+        assert!(matches!(
+            run(&mut registry, &["remove", "Steve"]),
+            Err(AdminError::Registry(RegistryError::Ambiguous(_, _)))
+        ));
+        run(&mut registry, &["remove", "#3"]).unwrap();
+        assert_eq!(registry.resolve("Steve"), Ok(2));
+    }
+
+    #[test]
+    fn setting_the_action() {
+        let mut registry = registry();
+        let report = run(&mut registry, &["set-action", "Bert", "probation"]).unwrap();
+        assert_eq!(report.lines, ["Bert (#1): action accept -> probation"]);
+        assert_eq!(report.probation, Some((1, Transition::Started)));
+        assert_eq!(
+            registry.get(1).unwrap().probation_since,
+            Some(today().start())
+        );
+
+        let report = run(
+            &mut registry,
+            &[
+                "set-action",
+                "Bert",
+                "accept_with_note",
+                "--note",
+                "Likes cider",
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            report.lines,
+            ["Bert (#1): action probation -> accept_with_note (Likes cider)"]
+        );
+        assert_eq!(report.probation, Some((1, Transition::Ended)));
+        assert_eq!(registry.get(1).unwrap().probation_since, None);
+        let report = run(&mut registry, &["set-action", "Bert", "refuse"]).unwrap();
+        assert_eq!(report.probation, None);
+
+        let mut error = |line: &[&str]| run(&mut registry, line).unwrap_err();
+        assert!(matches!(
+            error(&["set-action", "Bert"]),
+            AdminError::Usage(_)
+        ));
+        assert!(matches!(
+            error(&["set-action", "Bert", "accept_with_note"]),
+            AdminError::Invalid(_)
+        ));
+        assert!(matches!(
+            error(&["set-action", "Bert", "refuse", "--note", "x"]),
+            AdminError::Invalid(_)
+        ));
+        assert!(matches!(
+            error(&["set-action", "Bert", "ban"]),
+            AdminError::Invalid(_)
+        ));
+        assert!(matches!(
+            error(&["set-action", "Fred", "refuse"]),
+            AdminError::Registry(RegistryError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn adding_and_removing_aliases() {
+        let mut registry = registry();
+        let report = run(&mut registry, &["add-alias", "#2", "Stevo"]).unwrap();
+        assert_eq!(report.lines, ["Steve (#2): aliases are now Stevo"]);
+        assert_eq!(registry.resolve("stevo"), Ok(2));
+
+        assert_eq!(
+            run(&mut registry, &["add-alias", "#3", "Stevo"]),
+            Err(AdminError::Registry(RegistryError::AliasTaken {
+                alias: "Stevo".to_string(),
+                owner: 2
+            }))
+        );
+        assert!(matches!(
+            run(&mut registry, &["add-alias", "Bert"]),
+            Err(AdminError::Usage(_))
+        ));
+
+        let report = run(&mut registry, &["remove-alias", "#2", "STEVO"]).unwrap();
+        assert_eq!(report.lines, ["Steve (#2): removed alias Stevo"]);
+        assert_eq!(
+            run(&mut registry, &["remove-alias", "#2", "Stevo"]),
+            Err(AdminError::Registry(RegistryError::NotFound(
+                "Stevo".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn unknown_commands() {
+        assert_eq!(
+            parse_command("frobnicate", &[], today()),
+            Err(AdminError::Invalid(
+                "unknown command `frobnicate`".to_string()
+            ))
+        );
+    }
+}
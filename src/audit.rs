@@ -0,0 +1,397 @@
+// The audit log, audit.log in the data directory, holds every arrival at the door and every change
+// an admin made to the visitor list, in the order they happened. It uses the same [section] /
+// key = value format as the visitor list, with three extra keys at the end of every entry:
+//
+//     [admin]
+//     time = 2026-10-18T14:05:00Z
+//     command = set-action
+//     change = stevo (#2): action accept -> refuse
+//     seq = 7
+//     prev = 3f1c...e9   (the hash of entry 6)
+//     hash = a40b...12   (the hash of this entry)
+//
+// hash is the SHA-256 of the entry as written, without its own hash line. Because prev is part of
+// what gets hashed, every entry commits to the one before it, and so to the whole history:
+// * editing an entry changes what its hash should be,
+// * moving or deleting an entry breaks the seq numbers and the prev links of the entries after it.
+// The first entry's prev is GENESIS.
+//
+// The chain is plain, unkeyed SHA-256. There is no secret in it, so it only catches careless or
+// partial edits. Anyone who can write the file can change whatever they like and then recompute every
+// seq, prev and hash after it, and the result verifies just as well as the original. The same goes for
+// deleting entries from the very end, which leaves a shorter chain that is still valid.
+// Nothing in the file alone can show either. verify prints the number of entries and the head hash (the
+// last entry's), and only comparing those against a copy kept somewhere else, out of reach of whoever
+// can write the log, shows that the history was rewritten or cut short.
+//
+// Entries are only ever added to the end of the file, never rewritten.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::clock::Timestamp;
+use crate::storage::{parse_records, write_records, Record, StorageError};
+
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+// Keys the log adds to every entry. Entries written by callers must not use them.
+const CHAIN_KEYS: [&str; 3] = ["seq", "prev", "hash"];
+
+// What a successful verify found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verified {
+    pub entries: usize,
+    pub head: String, // hash of the last entry, GENESIS for an empty log.
+}
+
+// The first entry that doesn't fit the chain. position counts entries from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Broken {
+    pub position: usize,
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for Broken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "entry {} (line {}): {}",
+            self.position, self.line, self.reason
+        )
+    }
+}
+
+// The hash covers the entry without its hash line. Values are trimmed first because that is how
+// they read back from the file, so an entry hashes the same before and after it was written.
+fn entry_hash(entry: &Record) -> String {
+    let mut unhashed = Record::new(&entry.kind);
+    for (key, value) in &entry.fields {
+        if key != "hash" {
+            unhashed.push(key, value.trim());
+        }
+    }
+    let digest = Sha256::digest(write_records(&[unhashed]).as_bytes());
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Builds the [admin] entry for a change to the visitor list. Each line of the report becomes a change key.
+pub fn admin_entry(time: Timestamp, command: &str, changes: &[String]) -> Record {
+    let mut entry = Record::new("admin");
+    entry.push("time", time);
+    entry.push("command", command);
+    for change in changes {
+        entry.push("change", change);
+    }
+    entry
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn io_error(&self, source: io::Error) -> StorageError {
+        StorageError::Io {
+            path: self.path.clone(),
+            source,
+        }
+    }
+
+    // Every entry in the order it was written, chain keys included. A log that doesn't exist yet is empty.
+    pub fn entries(&self) -> Result<Vec<Record>, StorageError> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => parse_records(&text).map_err(|error| error.in_file(&self.path)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(source) => Err(self.io_error(source)),
+        }
+    }
+
+    // Adds entry to the end of the chain. An entry that already has chain keys is refused and
+    // nothing is written, the log has to be the only one setting them.
+    pub fn append(&self, entry: &Record) -> Result<(), StorageError> {
+        if let Some((key, _)) = entry
+            .fields
+            .iter()
+            .find(|(key, _)| CHAIN_KEYS.contains(&key.as_str()))
+        {
+            return Err(self.io_error(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "[{}] entry sets `{}`, only the audit log itself may set seq, prev or hash",
+                    entry.kind, key
+                ),
+            )));
+        }
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|source| self.io_error(source))?;
+        }
+        // append(true) means every write goes to the end of the file, whatever is already in it.
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|source| self.io_error(source))?;
+        // Another door or admin appending at the same time would link on the same last entry.
+        // The exclusive lock makes them wait until this entry is written, and is let go when file is dropped.
+        file.lock().map_err(|source| self.io_error(source))?;
+
+        // The last entry is where the new one links on. Its hash is taken from the file as it is,
+        // so appending to a damaged log doesn't hide the damage, verify still finds it.
+        let (seq, prev) = match self.last_entry(&mut file)? {
+            Some(last) => {
+                let prev = last.get("hash").unwrap_or(GENESIS).to_string();
+                match last.get("seq").and_then(|seq| seq.parse::<usize>().ok()) {
+                    Some(seq) => (seq + 1, prev),
+                    // Without a seq to go on, count the entries like verify does.
+                    None => (self.entries()?.len() + 1, prev),
+                }
+            }
+            None => (1, GENESIS.to_string()),
+        };
+
+        let mut chained = entry.clone();
+        chained.push("seq", seq);
+        chained.push("prev", prev);
+        let hash = entry_hash(&chained);
+        chained.push("hash", hash);
+
+        let text = format!("{}\n", write_records(&[chained]));
+        file.write_all(text.as_bytes())
+            .and_then(|_| file.sync_all())
+            .map_err(|source| self.io_error(source))
+    }
+
+    // The last entry in the file, found by reading backwards from the end to its [section] header,
+    // so arriving doesn't get slower as the log grows.
+    fn last_entry(&self, file: &mut File) -> Result<Option<Record>, StorageError> {
+        const CHUNK: u64 = 4096;
+        let io_error = |source| self.io_error(source);
+        let end = file.seek(SeekFrom::End(0)).map_err(io_error)?;
+        let mut start = end;
+        let mut tail: Vec<u8> = Vec::new();
+        loop {
+            // A header is at the start of the file or right after a line break.
+            let header = tail
+                .windows(2)
+                .rposition(|pair| pair == b"\n[")
+                .map(|index| index + 1)
+                .or_else(|| (start == 0 && tail.first() == Some(&b'[')).then_some(0));
+            if let Some(index) = header {
+                tail.drain(..index);
+                break;
+            }
+            if start == 0 {
+                break;
+            }
+            let read = CHUNK.min(start);
+            start -= read;
+            let mut chunk = vec![0; read as usize];
+            file.seek(SeekFrom::Start(start)).map_err(io_error)?;
+            file.read_exact(&mut chunk).map_err(io_error)?;
+            chunk.extend_from_slice(&tail);
+            tail = chunk;
+        }
+
+        // Anything odd about the tail is reported the way a full read reports it, with the right line.
+        let last = std::str::from_utf8(&tail)
+            .ok()
+            .and_then(|text| parse_records(text).ok())
+            .map(|mut records| records.pop());
+        match last {
+            Some(last) => Ok(last),
+            None => Ok(self.entries()?.pop()),
+        }
+    }
+
+    // Walks the chain from the start and stops at the first entry that doesn't fit.
+    // The outer Result is for a log that can't be read at all, the inner one is the verdict.
+    pub fn verify(&self) -> Result<Result<Verified, Broken>, StorageError> {
+        let entries = self.entries()?;
+        let mut prev = GENESIS.to_string();
+
+        for (index, entry) in entries.iter().enumerate() {
+            let position = index + 1;
+            let broken = |reason: String| {
+                Ok(Err(Broken {
+                    position,
+                    line: entry.line,
+                    reason,
+                }))
+            };
+
+            match entry.get("seq") {
+                Some(seq) if seq == position.to_string() => {}
+                Some(seq) => {
+                    return broken(format!(
+                        "has seq {} where {} was expected, entries were deleted or moved",
+                        seq, position
+                    ))
+                }
+                None => return broken("has no seq, it was not written by the log".to_string()),
+            }
+            match entry.get("prev") {
+                Some(link) if link == prev => {}
+                Some(_) => {
+                    return broken(
+                        "does not link to the entry before it, entries were deleted or moved"
+                            .to_string(),
+                    )
+                }
+                None => return broken("has no prev, it was not written by the log".to_string()),
+            }
+            match entry.get("hash") {
+                Some(hash) if hash == entry_hash(entry) => prev = hash.to_string(),
+                Some(_) => {
+                    return broken("its contents were changed after it was written".to_string())
+                }
+                None => return broken("has no hash, it was not written by the log".to_string()),
+            }
+        }
+
+        Ok(Ok(Verified {
+            entries: entries.len(),
+            head: prev,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A log in its own file under the system temp directory, removed again when the test is done.
+    struct TempLog(AuditLog);
+
+    impl TempLog {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "treehouse-audit-{}-{}.log",
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_file(&path);
+            Self(AuditLog::new(path))
+        }
+    }
+
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(self.0.path());
+        }
+    }
+
+    fn write_three(log: &AuditLog) {
+        let time = Timestamp::parse("2026-10-18T14:05:00Z").unwrap();
+        for command in ["add", "set-action", "remove"] {
+            log.append(&admin_entry(time, command, &["changed".to_string()]))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn an_untouched_log_verifies() {
+        let log = TempLog::new("untouched");
+        assert_eq!(
+            log.0.verify().unwrap(),
+            Ok(Verified {
+                entries: 0,
+                head: GENESIS.to_string()
+            })
+        );
+        write_three(&log.0);
+        let verified = log.0.verify().unwrap().unwrap();
+        assert_eq!(verified.entries, 3);
+        assert_eq!(
+            Some(verified.head.as_str()),
+            log.0.entries().unwrap()[2].get("hash")
+        );
+    }
+
+    #[test]
+    fn a_changed_line_is_found() {
+        let log = TempLog::new("tampered");
+        write_three(&log.0);
+        let text = fs::read_to_string(log.0.path()).unwrap();
+        fs::write(
+            log.0.path(),
+            text.replacen("command = set-action", "command = set-note", 1),
+        )
+        .unwrap();
+
+        let broken = log.0.verify().unwrap().unwrap_err();
+        assert_eq!(broken.position, 2);
+        assert!(broken.reason.contains("changed"));
+    }
+
+    #[test]
+    fn a_deleted_entry_is_found() {
+        let log = TempLog::new("deleted");
+        write_three(&log.0);
+        let mut entries = log.0.entries().unwrap();
+        entries.remove(1);
+        fs::write(log.0.path(), write_records(&entries)).unwrap();
+
+        let broken = log.0.verify().unwrap().unwrap_err();
+        assert_eq!(broken.position, 2);
+        assert!(broken.reason.contains("seq"));
+    }
+
+    #[test]
+    fn appends_at_the_same_time_stay_in_one_chain() {
+        let log = TempLog::new("concurrent");
+        write_three(&log.0);
+        // Each thread opens the file itself, like a door and an admin in two terminals.
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| write_three(&log.0));
+            }
+        });
+        assert_eq!(log.0.verify().unwrap().unwrap().entries, 27);
+    }
+
+    #[test]
+    fn a_log_without_seq_keeps_counting() {
+        let log = TempLog::new("unsequenced");
+        fs::write(
+            log.0.path(),
+            "[admin]\ncommand = add\n\n[admin]\ncommand = remove\n",
+        )
+        .unwrap();
+        write_three(&log.0);
+        let entries = log.0.entries().unwrap();
+        assert_eq!(entries[4].get("seq"), Some("5"));
+        assert_eq!(entries[3].get("prev"), entries[2].get("hash"));
+    }
+
+    #[test]
+    fn entries_with_chain_keys_are_refused() {
+        let log = TempLog::new("chain-keys");
+        let time = Timestamp::parse("2026-10-18T14:05:00Z").unwrap();
+        for key in CHAIN_KEYS {
+            let mut entry = admin_entry(time, "add", &[]);
+            entry.push(key, "1");
+            let error = log.0.append(&entry).unwrap_err();
+            assert!(
+                matches!(&error, StorageError::Io { source, .. } if source.kind() == io::ErrorKind::InvalidInput)
+            );
+            assert!(error.to_string().contains(key));
+        }
+        assert!(!log.0.path().exists());
+    }
+}
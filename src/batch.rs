@@ -0,0 +1,330 @@
+// Batch mode pre-screens a whole guest list at once, using the same lookup as the door.
+//
+// Input is one name per line, or a CSV file whose first column is the name.
+// A first row whose first column is `name` is treated as a header and skipped.
+//
+// Output is CSV with the header `name,id,decision,enrolled,note`, where decision is one of
+// accept, accept_with_note, refuse, probation, unknown or ambiguous.
+// id is empty unless the name matched exactly one visitor. For ambiguous names the note
+// lists the ids of every visitor with that name, and for unknown names it lists any
+// "did you mean" suggestions (see fuzzy.rs).
+
+use crate::clock::Timestamp;
+use crate::fuzzy::{self, FuzzyMatching};
+use crate::names::display_name;
+use crate::{Lookup, Visitor, VisitorAction, VisitorId, VisitorRegistry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchDecision {
+    Accept,
+    AcceptWithNote,
+    Refuse,
+    Probation,
+    Unknown,
+    Ambiguous,
+}
+
+impl BatchDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BatchDecision::Accept => "accept",
+            BatchDecision::AcceptWithNote => "accept_with_note",
+            BatchDecision::Refuse => "refuse",
+            BatchDecision::Probation => "probation",
+            BatchDecision::Unknown => "unknown",
+            BatchDecision::Ambiguous => "ambiguous",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchEntry {
+    pub name: String,
+    pub id: Option<VisitorId>,
+    pub decision: BatchDecision,
+    pub enrolled: bool, // true when this check added the name to the registry.
+    pub note: Option<String>,
+}
+
+impl BatchEntry {
+    fn for_visitor(name: &str, visitor: &Visitor) -> Self {
+        let (decision, note) = match &visitor.action {
+            VisitorAction::Accept => (BatchDecision::Accept, None),
+            VisitorAction::AcceptWithNote { note } => {
+                (BatchDecision::AcceptWithNote, Some(note.clone()))
+            }
+            VisitorAction::Refuse => (BatchDecision::Refuse, None),
+            VisitorAction::Probation => (BatchDecision::Probation, None),
+        };
+        Self {
+            name: name.to_string(),
+            id: Some(visitor.id),
+            decision,
+            enrolled: false,
+            note,
+        }
+    }
+}
+
+// Pulls the names out of a plain list or a CSV file. Blank lines are skipped.
+pub fn read_names(text: &str) -> Vec<String> {
+    let mut names: Vec<String> = text
+        .lines()
+        .map(first_csv_field)
+        .map(|name| display_name(&name))
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    if names
+        .first()
+        .is_some_and(|first| first.eq_ignore_ascii_case("name"))
+    {
+        names.remove(0);
+    }
+    names
+}
+
+// Checks every name against the registry. The registry is only changed when enroll_unknown is true,
+// in which case unknown names are added as probationary visitors, just like at the door.
+// Their probation starts at now, see probation.rs.
+pub fn check_names(
+    registry: &mut VisitorRegistry,
+    names: &[String],
+    enroll_unknown: bool,
+    fuzzy: FuzzyMatching,
+    now: Timestamp,
+) -> Vec<BatchEntry> {
+    let mut entries = Vec::new();
+    for name in names {
+        let entry = match registry.lookup(name) {
+            Lookup::Found(visitor) => BatchEntry::for_visitor(name, visitor),
+            Lookup::Ambiguous(visitors) => {
+                let ids: Vec<String> = visitors
+                    .iter()
+                    .map(|visitor| format!("#{}", visitor.id))
+                    .collect();
+                BatchEntry {
+                    name: name.clone(),
+                    id: None,
+                    decision: BatchDecision::Ambiguous,
+                    enrolled: false,
+                    note: Some(ids.join(" ")),
+                }
+            }
+            // nobody is there to ask for a birth date.
+            Lookup::NotFound if enroll_unknown => {
+                let mut newcomer = Visitor::probationary(name, None);
+                newcomer.probation_since = Some(now);
+                match registry.add(newcomer) {
+                    Ok(id) => {
+                        let visitor = registry.get(id).expect("visitor was just added");
+                        BatchEntry {
+                            enrolled: true,
+                            ..BatchEntry::for_visitor(name, visitor)
+                        }
+                    }
+                    // A new visitor has no aliases to clash, but the ids can run out.
+                    Err(error) => BatchEntry {
+                        name: name.clone(),
+                        id: None,
+                        decision: BatchDecision::Unknown,
+                        enrolled: false,
+                        note: Some(error.to_string()),
+                    },
+                }
+            }
+            Lookup::NotFound => {
+                let suggestions: Vec<String> = fuzzy::suggestions(registry, name, fuzzy)
+                    .iter()
+                    .map(|suggestion| format!("{} (#{})", suggestion.name, suggestion.id))
+                    .collect();
+                BatchEntry {
+                    name: name.clone(),
+                    id: None,
+                    decision: BatchDecision::Unknown,
+                    enrolled: false,
+                    note: (!suggestions.is_empty())
+                        .then(|| format!("did you mean {}?", suggestions.join(" or "))),
+                }
+            }
+        };
+        entries.push(entry);
+    }
+    entries
+}
+
+pub fn format_csv(entries: &[BatchEntry]) -> String {
+    let mut text = String::from("name,id,decision,enrolled,note\n");
+    for entry in entries {
+        text.push_str(&format!(
+            "{},{},{},{},{}\n",
+            csv_field(&entry.name),
+            entry.id.map(|id| id.to_string()).unwrap_or_default(),
+            entry.decision.as_str(),
+            if entry.enrolled { "yes" } else { "no" },
+            csv_field(entry.note.as_deref().unwrap_or("")),
+        ));
+    }
+    text
+}
+
+// Quotes a field when it contains a comma, a quote or a line break, doubling any quotes inside it.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn first_csv_field(line: &str) -> String {
+    let line = line.trim();
+    let Some(quoted) = line.strip_prefix('"') else {
+        // split always yields at least one piece, so next() can't be None here.
+        return line.split(',').next().unwrap_or("").to_string();
+    };
+
+    let mut field = String::new();
+    let mut chars = quoted.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '"' {
+            // "" inside a quoted field is an escaped quote, a single " ends the field.
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                field.push('"');
+            } else {
+                break;
+            }
+        } else {
+            field.push(c);
+        }
+    }
+    field
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> VisitorRegistry {
+        let mut registry = VisitorRegistry::new();
+        let note = VisitorAction::AcceptWithNote {
+            note: "Likes cider".to_string(),
+        };
+        for (name, action) in [
+            ("Bert", note),
+            ("Steve", VisitorAction::Accept),
+            ("Steve", VisitorAction::Accept),
+            ("Fred", VisitorAction::Refuse),
+        ] {
+            registry
+                .add(Visitor::new(name, "Hi", action, None))
+                .unwrap();
+        }
+        registry
+    }
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn csv_fields_are_unquoted() {
+        assert_eq!(first_csv_field("Bert,45,accept"), "Bert");
+        assert_eq!(first_csv_field("  Bert  "), "Bert");
+        assert_eq!(first_csv_field("\"Smith, John\",45"), "Smith, John");
+        assert_eq!(
+            first_csv_field("\"Bert \"\"B\"\" Jones\",45"),
+            "Bert \"B\" Jones"
+        );
+        // A missing closing quote takes the rest of the line.
+        assert_eq!(first_csv_field("\"Bert"), "Bert");
+        assert_eq!(first_csv_field(""), "");
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("Bert"), "Bert");
+        assert_eq!(csv_field("Smith, John"), "\"Smith, John\"");
+        assert_eq!(csv_field("Bert \"B\""), "\"Bert \"\"B\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(
+            first_csv_field(&csv_field("Bert \"B\", Jr")),
+            "Bert \"B\", Jr"
+        );
+    }
+
+    #[test]
+    fn names_come_from_the_first_column() {
+        let text = "Name,age\nBert,45\n\n   \n  steve   jones ,12\n\"Smith, John\",30\nname\n";
+        assert_eq!(
+            read_names(text),
+            ["Bert", "steve jones", "Smith, John", "name"]
+        );
+        // Only a first row is a header.
+        assert_eq!(read_names("Bert\nname\n"), ["Bert", "name"]);
+        assert!(read_names("name\n\n").is_empty());
+    }
+
+    #[test]
+    fn names_are_checked_without_enrolling() {
+        let mut registry = registry();
+        let now = Timestamp::parse("2026-10-18T12:00:00Z").unwrap();
+        let entries = check_names(
+            &mut registry,
+            &names(&["bert", "Steve", "Fred", "Bertt", "Zebedee"]),
+            false,
+            FuzzyMatching::default(),
+            now,
+        );
+        let decisions: Vec<_> = entries.iter().map(|entry| entry.decision).collect();
+        assert_eq!(
+            decisions,
+            [
+                BatchDecision::AcceptWithNote,
+                BatchDecision::Ambiguous,
+                BatchDecision::Refuse,
+                BatchDecision::Unknown,
+                BatchDecision::Unknown
+            ]
+        );
+        assert_eq!(entries[0].id, Some(1));
+        assert_eq!(entries[0].note.as_deref(), Some("Likes cider"));
+        assert_eq!(entries[1].note.as_deref(), Some("#2 #3"));
+        assert_eq!(entries[3].note.as_deref(), Some("did you mean Bert (#1)?"));
+        assert_eq!(entries[4].note, None);
+        assert_eq!(registry.len(), 4);
+
+        let csv = format_csv(&entries);
+        assert!(csv.starts_with(
+            "name,id,decision,enrolled,note\nbert,1,accept_with_note,no,Likes cider\n"
+        ));
+        assert_eq!(
+            read_names(&csv),
+            ["bert", "Steve", "Fred", "Bertt", "Zebedee"]
+        );
+    }
+
+    #[test]
+    fn unknown_names_can_be_enrolled() {
+        let mut registry = registry();
+        let now = Timestamp::parse("2026-10-18T12:00:00Z").unwrap();
+        let list = names(&["Bert", "Zebedee"]);
+        let entries = check_names(&mut registry, &list, true, FuzzyMatching::default(), now);
+        assert!(!entries[0].enrolled);
+        assert!(entries[1].enrolled);
+        assert_eq!(entries[1].id, Some(5));
+        assert_eq!(entries[1].decision, BatchDecision::Probation);
+
+        let zebedee = registry.get(5).unwrap();
+        assert_eq!(zebedee.action, VisitorAction::Probation);
+        assert_eq!(zebedee.probation_since, Some(now));
+        assert_eq!(zebedee.birth_date, None);
+
+        // A second check finds them instead of enrolling them again.
+        let again = check_names(&mut registry, &list, true, FuzzyMatching::default(), now);
+        assert!(!again[1].enrolled);
+        assert_eq!(again[1].id, Some(5));
+        assert_eq!(registry.len(), 5);
+    }
+}
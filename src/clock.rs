@@ -0,0 +1,425 @@
+// Time for the visit log and anything else that needs to know when something happened.
+//
+// Times are kept as whole seconds since 1970-01-01 00:00:00 UTC and written in ISO 8601 form,
+// e.g. 2026-10-18T14:05:00Z. Converting between days and calendar dates uses Howard Hinnant's
+// civil date algorithms, so no date library is needed.
+//
+// Code that needs the current time takes a &dyn Clock instead of asking the system directly,
+// which lets tests and simulations pick any time they like with FixedClock.
+//
+// Everything stored is in UTC. Anything that depends on the hour or the day people see on their own
+// clocks, like greetings or opening hours, first moves the time by the UtcOffset from the config:
+//
+//     [time]
+//     utc_offset = +01:00
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(pub i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32, // 1 to 12
+    pub day: u32,   // 1 to 31
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+// How far local time is ahead of UTC, in seconds. Negative west of Greenwich.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UtcOffset(pub i64);
+
+pub trait Clock {
+    fn now(&self) -> Timestamp;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        // duration_since only fails if the system clock is set before 1970.
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or(0);
+        Timestamp(seconds)
+    }
+}
+
+// A clock that always says the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub Timestamp);
+
+impl Clock for FixedClock {
+    fn now(&self) -> Timestamp {
+        self.0
+    }
+}
+
+// Lets a clock picked while the program runs, a Box<dyn Clock>, go wherever a clock is expected.
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now(&self) -> Timestamp {
+        (**self).now()
+    }
+}
+
+impl Date {
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+        let date = Self { year, month, day };
+        // A date is valid if it survives a round trip through a day count unchanged, so 2026-02-30 is rejected.
+        let valid = (1..=12).contains(&month)
+            && (1..=31).contains(&day)
+            && Date::from_days(date.days()) == date;
+        valid.then_some(date)
+    }
+
+    // Days since 1970-01-01.
+    pub fn days(&self) -> i64 {
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = i64::from(self.month);
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    pub fn from_days(days: i64) -> Self {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        } as u32;
+        let year = (year_of_era + era * 400) as i32 + i32::from(month <= 2);
+        Self { year, month, day }
+    }
+
+    // 1970-01-01 was a Thursday.
+    pub fn weekday(&self) -> Weekday {
+        Weekday::ALL[(self.days() + 3).rem_euclid(7) as usize]
+    }
+
+    pub fn is_leap_year(year: i32) -> bool {
+        year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+    }
+
+    // The first second of this date.
+    pub fn start(&self) -> Timestamp {
+        Timestamp(self.days() * SECONDS_PER_DAY)
+    }
+
+    // Parses YYYY-MM-DD.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.trim().splitn(3, '-');
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next()?.parse().ok()?;
+        let day = parts.next()?.parse().ok()?;
+        Date::new(year, month, day)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Weekday::Monday => "monday",
+            Weekday::Tuesday => "tuesday",
+            Weekday::Wednesday => "wednesday",
+            Weekday::Thursday => "thursday",
+            Weekday::Friday => "friday",
+            Weekday::Saturday => "saturday",
+            Weekday::Sunday => "sunday",
+        }
+    }
+
+    // Accepts the full name or the first three letters, in any case.
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.to_ascii_lowercase();
+        Weekday::ALL.into_iter().find(|day| {
+            day.label() == label || (label.len() == 3 && day.label().starts_with(&label))
+        })
+    }
+}
+
+impl UtcOffset {
+    // Parses +HH:MM or -HH:MM, and Z for UTC itself.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text == "Z" {
+            return Some(UtcOffset(0));
+        }
+        let (sign, rest) = match text.split_at_checked(1)? {
+            ("+", rest) => (1, rest),
+            ("-", rest) => (-1, rest),
+            _ => return None,
+        };
+        let (hours, minutes) = rest.split_once(':')?;
+        let hours: i64 = hours.parse().ok()?;
+        let minutes: i64 = minutes.parse().ok()?;
+        if hours > 14 || minutes > 59 || hours < 0 || minutes < 0 {
+            return None;
+        }
+        Some(UtcOffset(sign * (hours * 3600 + minutes * 60)))
+    }
+}
+
+impl fmt::Display for UtcOffset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { '-' } else { '+' };
+        let seconds = self.0.abs();
+        write!(
+            f,
+            "{}{:02}:{:02}",
+            sign,
+            seconds / 3600,
+            seconds % 3600 / 60
+        )
+    }
+}
+
+impl Timestamp {
+    // The same moment as the wall clock shows it at offset. Only use the result for its date and
+    // time of day, it is not a real UTC time any more and must never be stored.
+    pub fn to_local(self, offset: UtcOffset) -> Timestamp {
+        Timestamp(self.0 + offset.0)
+    }
+
+    pub fn date(&self) -> Date {
+        Date::from_days(self.0.div_euclid(SECONDS_PER_DAY))
+    }
+
+    // Seconds since midnight.
+    pub fn seconds_of_day(&self) -> i64 {
+        self.0.rem_euclid(SECONDS_PER_DAY)
+    }
+
+    // Parses the form Display writes, YYYY-MM-DDTHH:MM:SSZ.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (date, time) = text.trim().split_once('T')?;
+        let time = time.strip_suffix('Z')?;
+        let mut parts = time.splitn(3, ':');
+        let hour: i64 = parts.next()?.parse().ok()?;
+        let minute: i64 = parts.next()?.parse().ok()?;
+        let second: i64 = parts.next()?.parse().ok()?;
+        if hour > 23 || minute > 59 || second > 59 || hour < 0 || minute < 0 || second < 0 {
+            return None;
+        }
+        Some(Timestamp(
+            Date::parse(date)?.start().0 + hour * 3600 + minute * 60 + second,
+        ))
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = self.seconds_of_day();
+        write!(
+            f,
+            "{}T{:02}:{:02}:{:02}Z",
+            self.date(),
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        )
+    }
+}
+
+// A length of time for people to read, e.g. "2h 05m", "12m" or "40s".
+pub fn format_duration(seconds: i64) -> String {
+    let seconds = seconds.max(0); // a clock that went backwards shouldn't give a negative stay.
+    let (hours, minutes) = (seconds / 3600, seconds % 3600 / 60);
+    if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> Date {
+        Date::new(year, month, day).expect("test dates are valid")
+    }
+
+    #[test]
+    fn civil_dates_round_trip() {
+        assert_eq!(date(1970, 1, 1).days(), 0);
+        assert_eq!(date(1969, 12, 31).days(), -1);
+        assert_eq!(date(2000, 2, 29).days(), 11_016);
+        assert_eq!(date(2026, 10, 18).days(), 20_744);
+        assert_eq!(date(1600, 3, 1).days(), -135_080);
+        // Every day for a few thousand years either side of 1970, eras and negative years included.
+        for days in (-1_000_000..1_000_000).step_by(97) {
+            assert_eq!(Date::from_days(days).days(), days);
+        }
+        assert_eq!(Date::from_days(-1), date(1969, 12, 31));
+    }
+
+    #[test]
+    fn leap_years() {
+        assert!(Date::is_leap_year(2024));
+        assert!(Date::is_leap_year(2000));
+        assert!(!Date::is_leap_year(1900));
+        assert!(!Date::is_leap_year(2026));
+        assert_eq!(Date::new(2024, 2, 29), Some(date(2024, 2, 29)));
+        assert_eq!(Date::new(2000, 2, 29), Some(date(2000, 2, 29)));
+        assert_eq!(Date::new(1900, 2, 29), None);
+        assert_eq!(Date::new(2026, 2, 29), None);
+        assert_eq!(
+            Date::from_days(date(2024, 2, 28).days() + 1),
+            date(2024, 2, 29)
+        );
+        assert_eq!(
+            Date::from_days(date(2026, 2, 28).days() + 1),
+            date(2026, 3, 1)
+        );
+    }
+
+    #[test]
+    fn bad_dates_are_rejected() {
+        for (year, month, day) in [
+            (2026, 2, 30),
+            (2026, 4, 31),
+            (2026, 13, 1),
+            (2026, 0, 1),
+            (2026, 1, 0),
+        ] {
+            assert_eq!(Date::new(year, month, day), None);
+        }
+        assert_eq!(Date::parse(" 2026-10-18 "), Some(date(2026, 10, 18)));
+        for text in [
+            "",
+            "2026-10",
+            "2026/10/18",
+            "2026-10-18x",
+            "20261018",
+            "2026-02-30",
+        ] {
+            assert_eq!(Date::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn weekdays() {
+        assert_eq!(date(1970, 1, 1).weekday(), Weekday::Thursday);
+        assert_eq!(date(2026, 10, 18).weekday(), Weekday::Sunday);
+        assert_eq!(date(1969, 12, 29).weekday(), Weekday::Monday);
+        assert_eq!(Weekday::from_label("SAT"), Some(Weekday::Saturday));
+        assert_eq!(Weekday::from_label("sa"), None);
+    }
+
+    #[test]
+    fn timestamps_round_trip() {
+        for text in [
+            "1970-01-01T00:00:00Z",
+            "2026-10-18T14:05:09Z",
+            "2024-02-29T23:59:59Z",
+            "1969-12-31T23:59:59Z",
+        ] {
+            let time = Timestamp::parse(text).unwrap();
+            assert_eq!(time.to_string(), text);
+        }
+        assert_eq!(
+            Timestamp::parse("1969-12-31T23:59:59Z"),
+            Some(Timestamp(-1))
+        );
+        assert_eq!(
+            Timestamp::parse("2026-10-18T00:00:00Z"),
+            Some(date(2026, 10, 18).start())
+        );
+    }
+
+    #[test]
+    fn bad_timestamps_are_rejected() {
+        for text in [
+            "",
+            "2026-10-18",
+            "2026-10-18 14:05:00Z",
+            "2026-10-18T14:05:00",
+            "2026-10-18T14:05Z",
+            "2026-10-18T24:00:00Z",
+            "2026-10-18T14:60:00Z",
+            "2026-10-18T14:05:60Z",
+            "2026-10-18T-1:05:00Z",
+            "2026-02-29T12:00:00Z",
+        ] {
+            assert_eq!(Timestamp::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn utc_offsets() {
+        assert_eq!(UtcOffset::parse("Z"), Some(UtcOffset(0)));
+        assert_eq!(UtcOffset::parse("+01:00"), Some(UtcOffset(3600)));
+        assert_eq!(UtcOffset::parse("-05:30"), Some(UtcOffset(-19_800)));
+        assert_eq!(UtcOffset::parse("-00:30"), Some(UtcOffset(-1800)));
+        assert_eq!(UtcOffset::parse("+14:00"), Some(UtcOffset(50_400)));
+        for text in ["", "01:00", "+15:00", "+01:60", "+01", "+-1:00", "UTC"] {
+            assert_eq!(UtcOffset::parse(text), None, "{}", text);
+        }
+        assert_eq!(UtcOffset(-1800).to_string(), "-00:30");
+        assert_eq!(UtcOffset(-19_800).to_string(), "-05:30");
+        assert_eq!(UtcOffset(3600).to_string(), "+01:00");
+    }
+
+    #[test]
+    fn local_times_west_of_greenwich_can_be_the_day_before() {
+        let time = Timestamp::parse("2026-10-18T02:00:00Z").unwrap();
+        let local = time.to_local(UtcOffset::parse("-05:00").unwrap());
+        assert_eq!(local.date(), date(2026, 10, 17));
+        assert_eq!(local.seconds_of_day(), 21 * 3600);
+        assert_eq!(Timestamp(-1).date(), date(1969, 12, 31));
+        assert_eq!(Timestamp(-1).seconds_of_day(), SECONDS_PER_DAY - 1);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(format_duration(40), "40s");
+        assert_eq!(format_duration(12 * 60 + 5), "12m");
+        assert_eq!(format_duration(2 * 3600 + 5 * 60), "2h 05m");
+        assert_eq!(format_duration(-30), "0s");
+    }
+}
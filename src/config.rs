@@ -0,0 +1,391 @@
+// Settings that change how the treehouse behaves, read from treehouse.conf in the data directory.
+// The file uses the same [section] / key = value format as the visitor list (see storage.rs).
+// Every setting is optional and a missing file means "use the defaults".
+//
+//     [matching]
+//     # Treat "José" and "Jose" as the same name. Defaults to false.
+//     ignore_diacritics = true
+//
+//     [fuzzy]
+//     # "Did you mean ...?" suggestions for names that are nearly right, see fuzzy.rs.
+//     enabled = true
+//     max_distance = 2
+//     phonetic = true
+//
+//     [occupancy]
+//     # How many people fit inside and who waits first when it is full, see occupancy.rs.
+//     capacity = 8
+//     queue = fifo
+//
+//     [serving]
+//     # The legal drinking age, by jurisdiction code or given directly, see serving.rs.
+//     jurisdiction = us
+//     legal_age = 21
+//
+//     [time]
+//     # How far the treehouse clock is ahead of UTC, used for anything that depends on the hour.
+//     utc_offset = +01:00
+//
+//     [greetings]
+//     # Greetings for birthdays, days of the week and parts of the day, see greeting.rs.
+//     birthday = Happy birthday, {name}!
+//     friday = Happy Friday, {name}!
+//     evening = Good evening, {name}.
+//
+//     [group]
+//     # Opening hours shared by every visitor in the group, see access.rs. There can be many groups.
+//     name = juniors
+//     allow = weekdays 15:00-19:00
+//
+//     [minors]
+//     # A curfew for visitors under age, and whether an adult has to be with them, see minors.rs.
+//     age = 18
+//     allowed_hours = daily 09:00-21:00
+//     require_guardian = true
+//
+//     [sponsors]
+//     # Whether somebody new needs a member to vouch for them to get in, see sponsors.rs.
+//     required = true
+//
+//     [probation]
+//     # What it takes to be promoted from probation, and when it runs out, see probation.rs.
+//     visits = 3
+//     approvals = 2
+//     expire_after = 60
+//
+//     [locale]
+//     # The language for anyone without a preferred one, see messages.rs.
+//     default = en
+//
+// Unknown sections and keys are reported as errors, so a typo doesn't silently do nothing.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::access::{AccessGroup, AccessRule, Effect, Schedule};
+use crate::clock::{Date, Timestamp, UtcOffset, Weekday};
+use crate::decision::AdmissionContext;
+use crate::fuzzy::FuzzyMatching;
+use crate::greeting::{BirthdayGreeting, GreetingRules, PartOfDay};
+use crate::messages::{is_valid_language, normalize_language, LocaleSettings, Messages};
+use crate::minors::{Enforcement, MinorPolicy, UnknownAge};
+use crate::names::NameMatching;
+use crate::occupancy::{Occupancy, OccupancySettings, QueueOrder};
+use crate::probation::ProbationPolicy;
+use crate::serving::{ServingPolicy, JURISDICTIONS};
+use crate::sponsors::{SponsorSettings, Sponsorship};
+use crate::storage::{parse_records, ParseError, Record, StorageError};
+use crate::template::Template;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub matching: NameMatching,
+    pub fuzzy: FuzzyMatching,
+    pub occupancy: OccupancySettings,
+    pub serving: ServingPolicy,
+    pub utc_offset: UtcOffset,
+    pub greetings: GreetingRules,
+    pub locale: LocaleSettings,
+    pub groups: Vec<AccessGroup>,
+    pub minors: MinorPolicy,
+    pub sponsors: SponsorSettings,
+    pub probation: ProbationPolicy,
+}
+
+impl Config {
+    // The rules a decision at the door depends on, at the time now.
+    pub fn admission_context<'a>(
+        &'a self,
+        now: Timestamp,
+        occupancy: &'a Occupancy,
+        messages: &'a Messages,
+        sponsorships: &'a [Sponsorship],
+    ) -> AdmissionContext<'a> {
+        AdmissionContext {
+            now,
+            utc_offset: self.utc_offset,
+            serving: &self.serving,
+            greetings: &self.greetings,
+            occupancy,
+            messages,
+            groups: &self.groups,
+            minors: &self.minors,
+            sponsorships,
+        }
+    }
+
+    // The date on the treehouse's own calendar.
+    pub fn local_date(&self, now: Timestamp) -> Date {
+        now.to_local(self.utc_offset).date()
+    }
+}
+
+fn parse_bool(record: &Record, key: &str, value: &str) -> Result<bool, ParseError> {
+    match value {
+        "true" | "yes" | "on" => Ok(true),
+        "false" | "no" | "off" => Ok(false),
+        _ => Err(ParseError::new(
+            record.line,
+            format!("{} must be true or false, not `{}`", key, value),
+        )),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(
+    record: &Record,
+    key: &str,
+    value: &str,
+) -> Result<T, ParseError> {
+    value.parse::<T>().map_err(|_| {
+        ParseError::new(
+            record.line,
+            format!("{} must be a whole number, not `{}`", key, value),
+        )
+    })
+}
+
+fn unknown_key(record: &Record, key: &str) -> ParseError {
+    ParseError::new(
+        record.line,
+        format!("unknown key `{}` in [{}]", key, record.kind),
+    )
+}
+
+pub fn parse_config(text: &str) -> Result<Config, ParseError> {
+    let mut config = Config::default();
+
+    for record in parse_records(text)? {
+        match record.kind.as_str() {
+            "matching" => {
+                for (key, value) in &record.fields {
+                    match key.as_str() {
+                        "ignore_diacritics" => {
+                            config.matching.ignore_diacritics = parse_bool(&record, key, value)?
+                        }
+                        _ => return Err(unknown_key(&record, key)),
+                    }
+                }
+            }
+            "fuzzy" => {
+                for (key, value) in &record.fields {
+                    match key.as_str() {
+                        "enabled" => config.fuzzy.enabled = parse_bool(&record, key, value)?,
+                        "max_distance" => {
+                            config.fuzzy.max_distance = parse_number(&record, key, value)?
+                        }
+                        "phonetic" => config.fuzzy.phonetic = parse_bool(&record, key, value)?,
+                        _ => return Err(unknown_key(&record, key)),
+                    }
+                }
+            }
+            "occupancy" => {
+                for (key, value) in &record.fields {
+                    match key.as_str() {
+                        "capacity" => {
+                            config.occupancy.capacity = Some(parse_number(&record, key, value)?)
+                        }
+                        "queue" => {
+                            config.occupancy.queue_order = QueueOrder::from_label(value)
+                                .ok_or_else(|| {
+                                    ParseError::new(
+                                        record.line,
+                                        format!(
+                                            "queue must be fifo or members_first, not `{}`",
+                                            value
+                                        ),
+                                    )
+                                })?
+                        }
+                        _ => return Err(unknown_key(&record, key)),
+                    }
+                }
+            }
+            "serving" => {
+                // Applied after the loop, so legal_age wins wherever it is in the section.
+                let mut legal_age = None;
+                for (key, value) in &record.fields {
+                    match key.as_str() {
+                        "jurisdiction" => {
+                            config.serving =
+                                ServingPolicy::for_jurisdiction(value).ok_or_else(|| {
+                                    let known: Vec<&str> =
+                                        JURISDICTIONS.iter().map(|(code, _)| *code).collect();
+                                    ParseError::new(
+                                        record.line,
+                                        format!(
+                                            "unknown jurisdiction `{}`, expected one of {}",
+                                            value,
+                                            known.join(", ")
+                                        ),
+                                    )
+                                })?
+                        }
+                        "legal_age" => legal_age = Some(parse_number(&record, key, value)?),
+                        _ => return Err(unknown_key(&record, key)),
+                    }
+                }
+                if let Some(legal_age) = legal_age {
+                    config.serving.legal_age = legal_age;
+                }
+            }
+            "time" => {
+                for (key, value) in &record.fields {
+                    match key.as_str() {
+                        "utc_offset" => {
+                            config.utc_offset = UtcOffset::parse(value).ok_or_else(|| {
+                                ParseError::new(
+                                    record.line,
+                                    format!("utc_offset must look like +01:00, not `{}`", value),
+                                )
+                            })?
+                        }
+                        _ => return Err(unknown_key(&record, key)),
+                    }
+                }
+            }
+            "greetings" => {
+                for (key, value) in &record.fields {
+                    let template = || {
+                        Template::parse(value).map_err(|error| {
+                            ParseError::new(record.line, format!("{}: {}", key, error))
+                        })
+                    };
+                    // A later line for the same day or part of the day replaces an earlier one.
+                    if key == "birthday" {
+                        // An empty value turns birthday greetings off.
+                        config.greetings.birthday = if value.is_empty() {
+                            BirthdayGreeting::Off
+                        } else {
+                            BirthdayGreeting::Custom(template()?)
+                        };
+                    } else if let Some(day) = Weekday::from_label(key) {
+                        config.greetings.weekdays.retain(|(other, _)| *other != day);
+                        config.greetings.weekdays.push((day, template()?));
+                    } else if let Some(part) = PartOfDay::from_label(key) {
+                        config
+                            .greetings
+                            .parts_of_day
+                            .retain(|(other, _)| *other != part);
+                        config.greetings.parts_of_day.push((part, template()?));
+                    } else {
+                        return Err(unknown_key(&record, key));
+                    }
+                }
+            }
+            "group" => {
+                let name = record.require("name")?;
+                if config.groups.iter().any(|group| group.name == name) {
+                    return Err(ParseError::new(
+                        record.line,
+                        format!("there is more than one group called {}", name),
+                    ));
+                }
+                let mut group = AccessGroup {
+                    name: name.to_string(),
+                    rules: Vec::new(),
+                };
+                for (key, value) in &record.fields {
+                    match Effect::from_label(key) {
+                        Some(effect) => group.rules.push(
+                            AccessRule::parse(effect, value)
+                                .map_err(|message| ParseError::new(record.line, message))?,
+                        ),
+                        None if key == "name" => {}
+                        None => return Err(unknown_key(&record, key)),
+                    }
+                }
+                config.groups.push(group);
+            }
+            "minors" => {
+                let bad = |expected: &str, value: &str| {
+                    ParseError::new(record.line, format!("{}, not `{}`", expected, value))
+                };
+                for (key, value) in &record.fields {
+                    match key.as_str() {
+                        "age" => config.minors.age = parse_number(&record, key, value)?,
+                        "allowed_hours" => {
+                            config
+                                .minors
+                                .allowed_hours
+                                .push(Schedule::parse(value).ok_or_else(|| {
+                                    bad(
+                                        "allowed_hours must be a schedule like daily 09:00-21:00",
+                                        value,
+                                    )
+                                })?)
+                        }
+                        "outside_hours" => {
+                            config.minors.outside_hours = Enforcement::from_label(value)
+                                .ok_or_else(|| bad("outside_hours must be deny or flag", value))?
+                        }
+                        "require_guardian" => {
+                            config.minors.require_guardian = parse_bool(&record, key, value)?
+                        }
+                        "unknown_age" => {
+                            config.minors.unknown_age =
+                                UnknownAge::from_label(value).ok_or_else(|| {
+                                    bad("unknown_age must be flag, deny or minor", value)
+                                })?
+                        }
+                        _ => return Err(unknown_key(&record, key)),
+                    }
+                }
+            }
+            "sponsors" => {
+                for (key, value) in &record.fields {
+                    match key.as_str() {
+                        "required" => config.sponsors.required = parse_bool(&record, key, value)?,
+                        _ => return Err(unknown_key(&record, key)),
+                    }
+                }
+            }
+            "probation" => {
+                for (key, value) in &record.fields {
+                    let setting = match key.as_str() {
+                        "visits" => &mut config.probation.visits,
+                        "days" => &mut config.probation.days,
+                        "approvals" => &mut config.probation.approvals,
+                        "expire_after" => &mut config.probation.expire_after,
+                        _ => return Err(unknown_key(&record, key)),
+                    };
+                    *setting = Some(parse_number(&record, key, value)?);
+                }
+            }
+            "locale" => {
+                for (key, value) in &record.fields {
+                    match key.as_str() {
+                        "default" if is_valid_language(value) => {
+                            config.locale.default_language = normalize_language(value)
+                        }
+                        "default" => {
+                            return Err(ParseError::new(
+                                record.line,
+                                format!("default must be a language code like en, not `{}`", value),
+                            ))
+                        }
+                        _ => return Err(unknown_key(&record, key)),
+                    }
+                }
+            }
+            other => {
+                return Err(ParseError::new(
+                    record.line,
+                    format!("unknown section [{}]", other),
+                ))
+            }
+        }
+    }
+    Ok(config)
+}
+
+pub fn load_config(path: &Path) -> Result<Config, StorageError> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse_config(&text).map_err(|error| error.in_file(path)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(source) => Err(StorageError::Io {
+            path: path.to_path_buf(),
+            source,
+        }),
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt::Write;
+
+use crate::access::{AccessGroup, AccessVerdict};
+use crate::clock::{Timestamp, UtcOffset};
+use crate::greeting::GreetingRules;
+use crate::messages::{Messages, DEFAULT_LANGUAGE};
+use crate::minors::{Enforcement, MinorFinding, MinorIssue, MinorPolicy};
+use crate::occupancy::Occupancy;
+use crate::passes::{Lapse, PassStatus};
+use crate::serving::ServingPolicy;
+use crate::sponsors::Sponsorship;
+
+// What happens to a visitor at the door. This used to be a handful of println! calls,
+// now it is plain data so it can be tested, logged, or shown by any front end.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdmissionDecision {
+    pub visitor_name: String,
+    pub outcome: Outcome,
+    pub greeting: String,
+    pub notes: Vec<String>,
+    pub warnings: Vec<Warning>,
+    pub language: String, // what the visitor is spoken to in, see messages.rs.
+    pub access: Option<AccessVerdict>, // which access rule let them in or kept them out, if any applied.
+    pub minors: Vec<MinorFinding>,     // curfew and guardian rules they didn't meet, see minors.rs.
+    pub pass: Option<PassStatus>,      // for guests, see passes.rs.
+    pub sponsor: Option<String>,       // who vouched for a visitor on probation, see sponsors.rs.
+    pub vouched_for: Vec<Sponsorship>, // new visitors this one vouched for that they haven't heard about.
+}
+
+// Everything outside the visitor that a decision depends on, gathered up so
+// Visitor::admission_decision doesn't need a new parameter for every new rule.
+#[derive(Debug, Clone, Copy)]
+pub struct AdmissionContext<'a> {
+    pub now: Timestamp,
+    pub utc_offset: UtcOffset,
+    pub serving: &'a ServingPolicy,
+    pub greetings: &'a GreetingRules,
+    pub occupancy: &'a Occupancy, // who has been in before, for the {visits} and {last_visit} placeholders.
+    pub messages: &'a Messages,
+    pub groups: &'a [AccessGroup],
+    pub minors: &'a MinorPolicy,
+    pub sponsorships: &'a [Sponsorship], // every vouch so far, from the audit log.
+}
+
+impl AdmissionContext<'_> {
+    // The treehouse's wall clock, see Timestamp::to_local.
+    pub fn local_time(&self) -> Timestamp {
+        self.now.to_local(self.utc_offset)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Admitted,
+    Refused,
+    Probation,
+    Lapsed, // a guest whose pass isn't valid any more, or not yet. Not the same as being refused.
+}
+
+impl Outcome {
+    // Whether the visitor goes in (or into the queue when it is full).
+    pub fn lets_in(&self) -> bool {
+        matches!(self, Outcome::Admitted | Outcome::Probation)
+    }
+}
+
+// Warnings are for whoever is looking after the treehouse, not for the visitor.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    DoNotServeAlcohol,
+    CheckAge, // nobody knows how old they are, so ask for ID before serving alcohol.
+}
+
+impl AdmissionDecision {
+    pub fn new(visitor_name: &str, greeting: &str, outcome: Outcome) -> Self {
+        Self {
+            visitor_name: visitor_name.to_string(),
+            outcome,
+            greeting: greeting.to_string(),
+            notes: Vec::new(),
+            warnings: Vec::new(),
+            language: DEFAULT_LANGUAGE.to_string(),
+            access: None,
+            minors: Vec::new(),
+            pass: None,
+            sponsor: None,
+            vouched_for: Vec::new(),
+        }
+    }
+
+    pub fn is_admitted(&self) -> bool {
+        self.outcome == Outcome::Admitted
+    }
+}
+
+// A trait is a set of methods that any type can implement. Front ends implement this one
+// to show a decision their own way, e.g. as HTML, a log line or a chat message.
+pub trait DecisionRenderer {
+    fn render(&self, decision: &AdmissionDecision) -> String;
+}
+
+// Reproduces the lines the door has always printed to the terminal, in the visitor's language.
+#[derive(Debug, Clone, Copy)]
+pub struct TerminalRenderer<'a> {
+    pub messages: &'a Messages,
+}
+
+impl<'a> TerminalRenderer<'a> {
+    pub fn new(messages: &'a Messages) -> Self {
+        Self { messages }
+    }
+}
+
+impl DecisionRenderer for TerminalRenderer<'_> {
+    fn render(&self, decision: &AdmissionDecision) -> String {
+        let language = decision.language.as_str();
+        let say = |key: &str| {
+            self.messages
+                .text(language, key, &[("name", &decision.visitor_name)])
+        };
+        let mut text = String::new();
+        // writeln! on a String can't fail, the Result is only there because Write is shared with files.
+        let _ = writeln!(text, "{}", decision.greeting);
+
+        let outcome = match decision.outcome {
+            Outcome::Admitted => "welcome",
+            Outcome::Probation => "probation",
+            Outcome::Refused => "refused",
+            Outcome::Lapsed => "lapsed",
+        };
+        let _ = writeln!(text, "{}", say(outcome));
+        if let Some(sponsor) = &decision.sponsor {
+            let values = [
+                ("name", decision.visitor_name.as_str()),
+                ("sponsor", sponsor),
+            ];
+            let _ = writeln!(
+                text,
+                "{}",
+                self.messages.text(language, "sponsored_by", &values)
+            );
+        }
+        if let Some(status) = &decision.pass {
+            let name = ("name", decision.visitor_name.as_str());
+            let line = match status {
+                PassStatus::Valid { pass, uses } => {
+                    let sponsor = format!("#{}", pass.issued_by);
+                    let until = pass.valid_until.to_string();
+                    match pass.max_uses {
+                        Some(max) => {
+                            let uses = uses.to_string();
+                            let values = [
+                                name,
+                                ("sponsor", &sponsor),
+                                ("until", &until),
+                                ("uses", &uses),
+                            ];
+                            self.messages.plural(
+                                language,
+                                "pass_valid_limited",
+                                u64::from(max),
+                                &values,
+                            )
+                        }
+                        None => self.messages.text(
+                            language,
+                            "pass_valid",
+                            &[name, ("sponsor", &sponsor), ("until", &until)],
+                        ),
+                    }
+                }
+                PassStatus::Lapsed { lapse, .. } => match lapse {
+                    Lapse::NotYetValid(from) => self.messages.text(
+                        language,
+                        "pass_not_yet_valid",
+                        &[name, ("from", &from.to_string())],
+                    ),
+                    Lapse::Expired(until) => self.messages.text(
+                        language,
+                        "pass_expired",
+                        &[name, ("until", &until.to_string())],
+                    ),
+                    Lapse::UsedUp(max) => {
+                        self.messages
+                            .plural(language, "pass_used_up", u64::from(*max), &[name])
+                    }
+                },
+            };
+            let _ = writeln!(text, "{}", line);
+        }
+        if let Some(verdict) = &decision.access {
+            let name = ("name", decision.visitor_name.as_str());
+            let line = match verdict {
+                AccessVerdict::Allowed(rule) => self.messages.text(
+                    language,
+                    "access_allowed",
+                    &[name, ("rule", &rule.to_string())],
+                ),
+                AccessVerdict::Denied(rule) => self.messages.text(
+                    language,
+                    "access_denied",
+                    &[name, ("rule", &rule.to_string())],
+                ),
+                AccessVerdict::OutsideHours(rules) => {
+                    let rules: Vec<String> = rules.iter().map(ToString::to_string).collect();
+                    self.messages.text(
+                        language,
+                        "access_outside",
+                        &[name, ("rules", &rules.join("; "))],
+                    )
+                }
+                AccessVerdict::UnknownGroup(group) => {
+                    self.messages
+                        .text(language, "access_unknown_group", &[name, ("group", group)])
+                }
+            };
+            let _ = writeln!(text, "{}", line);
+        }
+        for finding in &decision.minors {
+            let under = finding.under.to_string();
+            let mut values = vec![("name", decision.visitor_name.clone()), ("age", under)];
+            let key = match &finding.issue {
+                MinorIssue::OutsideHours(hours) => {
+                    let hours: Vec<String> = hours.iter().map(ToString::to_string).collect();
+                    values.push(("hours", hours.join("; ")));
+                    "minor_outside_hours"
+                }
+                MinorIssue::NoGuardian => "minor_no_guardian",
+                MinorIssue::GuardianNotInside(guardians) => {
+                    let guardians: Vec<String> =
+                        guardians.iter().map(|id| format!("#{}", id)).collect();
+                    values.push(("guardians", guardians.join(", ")));
+                    "minor_guardian_not_inside"
+                }
+                MinorIssue::AgeUnknown => "minor_age_unknown",
+            };
+            let values: Vec<(&str, &str)> = values
+                .iter()
+                .map(|(word, value)| (*word, value.as_str()))
+                .collect();
+            let line = self.messages.text(language, key, &values);
+            // Flags let the visitor in anyway, so say that they are only a warning.
+            let line = match finding.enforcement {
+                Enforcement::Deny => line,
+                Enforcement::Flag => self.messages.text(language, "flagged", &[("issue", &line)]),
+            };
+            let _ = writeln!(text, "{}", line);
+        }
+        for sponsorship in &decision.vouched_for {
+            let values = [
+                ("name", decision.visitor_name.as_str()),
+                ("guest", &sponsorship.name),
+                ("time", &sponsorship.time.to_string()),
+            ];
+            let _ = writeln!(
+                text,
+                "{}",
+                self.messages.text(language, "sponsor_notice", &values)
+            );
+        }
+        for note in &decision.notes {
+            let _ = writeln!(text, "{}", note);
+        }
+        for warning in &decision.warnings {
+            let key = match warning {
+                Warning::DoNotServeAlcohol => "no_alcohol",
+                Warning::CheckAge => "check_id",
+            };
+            let _ = writeln!(text, "{}", say(key));
+        }
+        text
+    }
+}
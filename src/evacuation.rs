@@ -0,0 +1,149 @@
+// Emergency evacuation roll call.
+//
+// Starting an evacuation freezes the door, so nobody else is let in or taken off the waiting queue,
+// and takes a roll of everybody who was inside at that moment. A warden then marks each person as
+// accounted for once they are safely out. Ending the evacuation reports anyone still missing.
+//
+// Like occupancy, everything is kept in the audit log (see audit.rs) and worked out again by replaying it:
+//
+//     [evacuation]
+//     time = 2026-10-18T15:00:00Z
+//     event = start
+//
+//     [accounted]
+//     time = 2026-10-18T15:02:10Z
+//     visitor = 2
+//     name = Steve
+//
+//     [evacuation]
+//     time = 2026-10-18T15:10:00Z
+//     event = end
+//
+// The roll itself isn't written down: it is whoever the check-ins and check-outs before the start entry
+// say was inside.
+
+use crate::clock::Timestamp;
+use crate::occupancy::Occupancy;
+use crate::storage::{ParseError, Record};
+use crate::VisitorId;
+
+// One person on the roll. accounted is when the warden marked them, None while they are missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollEntry {
+    pub visitor: VisitorId,
+    pub name: String,
+    pub accounted: Option<Timestamp>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollCall {
+    pub started: Timestamp,
+    pub roll: Vec<RollEntry>,
+}
+
+// Why a visitor couldn't be marked as accounted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkError {
+    NotOnRoll,
+    AlreadyAccounted(Timestamp),
+}
+
+pub fn start_entry(time: Timestamp) -> Record {
+    event_entry(time, "start")
+}
+
+pub fn end_entry(time: Timestamp) -> Record {
+    event_entry(time, "end")
+}
+
+fn event_entry(time: Timestamp, event: &str) -> Record {
+    let mut entry = Record::new("evacuation");
+    entry.push("time", time);
+    entry.push("event", event);
+    entry
+}
+
+pub fn accounted_entry(time: Timestamp, visitor: VisitorId, name: &str) -> Record {
+    let mut entry = Record::new("accounted");
+    entry.push("time", time);
+    entry.push("visitor", visitor);
+    entry.push("name", name);
+    entry
+}
+
+impl RollCall {
+    // Takes the roll from everybody inside.
+    pub fn start(occupancy: &Occupancy, time: Timestamp) -> Self {
+        Self {
+            started: time,
+            roll: occupancy
+                .inside()
+                .map(|stay| RollEntry {
+                    visitor: stay.visitor,
+                    name: stay.name.clone(),
+                    accounted: None,
+                })
+                .collect(),
+        }
+    }
+
+    // The evacuation going on right now, if there is one, found by replaying the audit log.
+    pub fn from_entries(entries: &[Record]) -> Result<Option<Self>, ParseError> {
+        let mut occupancy = Occupancy::default();
+        let mut current: Option<RollCall> = None;
+
+        for entry in entries {
+            occupancy.apply(entry)?;
+            let error = |message: String| ParseError::new(entry.line, message);
+            let time = || {
+                let time = entry.require("time")?;
+                Timestamp::parse(time).ok_or_else(|| error(format!("bad time `{}`", time)))
+            };
+            match entry.kind.as_str() {
+                "evacuation" => match entry.require("event")? {
+                    "start" => current = Some(RollCall::start(&occupancy, time()?)),
+                    "end" => current = None,
+                    other => return Err(error(format!("unknown evacuation event `{}`", other))),
+                },
+                "accounted" => {
+                    let visitor = entry.require("visitor")?;
+                    let visitor = visitor
+                        .parse()
+                        .map_err(|_| error(format!("bad visitor id `{}`", visitor)))?;
+                    // Marks outside an evacuation, or repeated ones, have nothing left to change.
+                    if let Some(roll_call) = current.as_mut() {
+                        let _ = roll_call.mark(visitor, time()?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(current)
+    }
+
+    pub fn entry(&self, visitor: VisitorId) -> Option<&RollEntry> {
+        self.roll.iter().find(|entry| entry.visitor == visitor)
+    }
+
+    pub fn mark(&mut self, visitor: VisitorId, time: Timestamp) -> Result<&RollEntry, MarkError> {
+        let entry = self
+            .roll
+            .iter_mut()
+            .find(|entry| entry.visitor == visitor)
+            .ok_or(MarkError::NotOnRoll)?;
+        if let Some(accounted) = entry.accounted {
+            return Err(MarkError::AlreadyAccounted(accounted));
+        }
+        entry.accounted = Some(time);
+        Ok(entry)
+    }
+
+    // Everybody the warden hasn't seen yet.
+    pub fn missing(&self) -> impl Iterator<Item = &RollEntry> {
+        self.roll.iter().filter(|entry| entry.accounted.is_none())
+    }
+
+    pub fn accounted(&self) -> impl Iterator<Item = &RollEntry> {
+        self.roll.iter().filter(|entry| entry.accounted.is_some())
+    }
+}
@@ -0,0 +1,222 @@
+// Approximate name matching, used to ask "did you mean Steve?" before a typo like "stve"
+// gets enrolled as a brand new probationary visitor.
+//
+// Two tests are used, and a visitor is suggested if either one passes:
+// * edit distance: how many single letter insertions, deletions or substitutions turn one name into the other.
+// * Soundex: a four character code for how a name sounds in English, so "Steven" and "Stephen" match.
+//
+// Settings live in the [fuzzy] section of the config file:
+//
+//     [fuzzy]
+//     enabled = true      # set to false for strict security, only exact names are recognised
+//     max_distance = 2    # the largest edit distance still worth suggesting
+//     phonetic = true     # also suggest names that sound the same
+
+use crate::names::{name_key, NameMatching};
+use crate::{Visitor, VisitorId, VisitorRegistry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuzzyMatching {
+    pub enabled: bool,
+    pub max_distance: usize,
+    pub phonetic: bool,
+}
+
+impl Default for FuzzyMatching {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_distance: 2,
+            phonetic: true,
+        }
+    }
+}
+
+// Never suggest more than this many names, a long list is no help at the door.
+pub const MAX_SUGGESTIONS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub id: VisitorId,
+    pub name: String,
+    pub distance: usize,
+    pub sounds_alike: bool,
+}
+
+// Levenshtein distance, worked out one row of the usual table at a time.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // previous[j] is the distance between the first i letters of a and the first j letters of b.
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            let insertion = current[j] + 1;
+            let deletion = previous[j + 1] + 1;
+            current.push(substitution.min(insertion).min(deletion));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// American Soundex. Returns None for names without any ASCII letters, which have no code.
+pub fn soundex(name: &str) -> Option<String> {
+    fn digit(c: char) -> Option<char> {
+        match c {
+            'b' | 'f' | 'p' | 'v' => Some('1'),
+            'c' | 'g' | 'j' | 'k' | 'q' | 's' | 'x' | 'z' => Some('2'),
+            'd' | 't' => Some('3'),
+            'l' => Some('4'),
+            'm' | 'n' => Some('5'),
+            'r' => Some('6'),
+            _ => None, // vowels, h, w and y have no digit.
+        }
+    }
+
+    let letters: Vec<char> = name
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    let (&first, rest) = letters.split_first()?;
+
+    let mut code = first.to_ascii_uppercase().to_string();
+    let mut last = digit(first);
+    for &c in rest {
+        let current = digit(c);
+        if current.is_some() && current != last {
+            code.extend(current);
+        }
+        // h and w don't separate two letters with the same digit, vowels do.
+        if c != 'h' && c != 'w' {
+            last = current;
+        }
+        if code.len() == 4 {
+            break;
+        }
+    }
+    while code.len() < 4 {
+        code.push('0');
+    }
+    Some(code)
+}
+
+fn phonetic_key(name: &str) -> Option<String> {
+    // Accents don't change how a name sounds, so they are dropped before working out the code.
+    soundex(&name_key(
+        name,
+        NameMatching {
+            ignore_diacritics: true,
+        },
+    ))
+}
+
+// Visitors whose names are close to name, best matches first.
+// Exact matches are left out, they are found by the normal lookup.
+pub fn suggestions(
+    registry: &VisitorRegistry,
+    name: &str,
+    settings: FuzzyMatching,
+) -> Vec<Suggestion> {
+    if !settings.enabled {
+        return Vec::new();
+    }
+
+    let matching = registry.name_matching();
+    let key = name_key(name, matching);
+    let sound = if settings.phonetic {
+        phonetic_key(name)
+    } else {
+        None
+    };
+
+    let mut found: Vec<Suggestion> = registry
+        .iter()
+        .filter_map(|visitor: &Visitor| {
+            // Aliases count too, so "stevo" can be suggested for "stivo". The best of them is used.
+            let keys: Vec<String> = visitor.keys(matching).collect();
+            if keys.contains(&key) {
+                return None;
+            }
+            let distance = keys
+                .iter()
+                .map(|visitor_key| edit_distance(&key, visitor_key))
+                .min()?;
+            // A distance as long as the name itself means every letter was wrong, that's not a typo.
+            let close = distance <= settings.max_distance && distance < key.chars().count();
+            let sounds_alike = sound.is_some()
+                && std::iter::once(&visitor.name)
+                    .chain(&visitor.aliases)
+                    .any(|name| phonetic_key(name) == sound);
+            (close || sounds_alike).then(|| Suggestion {
+                id: visitor.id,
+                name: visitor.name.clone(),
+                distance,
+                sounds_alike,
+            })
+        })
+        .collect();
+
+    // Closest first, and for equal distances prefer a name that also sounds the same.
+    found.sort_by_key(|suggestion| (suggestion.distance, !suggestion.sounds_alike, suggestion.id));
+    found.truncate(MAX_SUGGESTIONS);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VisitorAction;
+
+    fn registry(names: &[&str]) -> VisitorRegistry {
+        let mut registry = VisitorRegistry::new();
+        for name in names {
+            registry
+                .add(Visitor::new(name, "Hi", VisitorAction::Accept, None))
+                .unwrap();
+        }
+        registry
+    }
+
+    fn suggested(registry: &VisitorRegistry, name: &str, settings: FuzzyMatching) -> Vec<String> {
+        suggestions(registry, name, settings)
+            .into_iter()
+            .map(|suggestion| suggestion.name)
+            .collect()
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("steve", "steve"), 0);
+        assert_eq!(edit_distance("stve", "steve"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "bert"), 4);
+    }
+
+    #[test]
+    fn soundex_codes() {
+        assert_eq!(soundex("robert").as_deref(), Some("R163"));
+        assert_eq!(soundex("rupert").as_deref(), Some("R163"));
+        assert_eq!(soundex("steven"), soundex("stephen"));
+    }
+
+    #[test]
+    fn typos_and_sound_alikes_are_suggested() {
+        let registry = registry(&["Bert", "Steven", "Fred"]);
+        let settings = FuzzyMatching::default();
+        assert_eq!(suggested(&registry, "stve", settings), ["Steven"]);
+        assert_eq!(suggested(&registry, "Stephen", settings), ["Steven"]);
+        assert_eq!(suggested(&registry, "Bert", settings), Vec::<String>::new());
+        assert_eq!(suggested(&registry, "Zz", settings), Vec::<String>::new());
+
+        let strict = FuzzyMatching {
+            enabled: false,
+            ..settings
+        };
+        assert!(suggested(&registry, "stve", strict).is_empty());
+    }
+}
@@ -0,0 +1,259 @@
+// Picks the greeting a visitor hears at the door.
+//
+// Every visitor has their own stored greeting. On top of that the [greetings] section of the config
+// file can set greetings for a birthday, a day of the week or a part of the day. The most specific one
+// that fits wins, in this order, and the stored greeting is used when none of them do:
+//
+//     [greetings]
+//     birthday = Happy birthday, {name}!   # see below
+//     friday = Happy Friday, {name}!
+//     morning = Good morning, {name}.     # 05:00 to 11:59
+//     afternoon = Good afternoon, {name}. # 12:00 to 16:59
+//     evening = Good evening, {name}.     # 17:00 to 21:59
+//     night = Still up, {name}?           # 22:00 to 04:59
+//
+// Greetings are templates, so {name}, {visits}, {last_visit} and {note} can be used in all of them,
+// see template.rs. The hour and day are local time, see clock.rs.
+//
+// Birthdays are greeted even without a birthday line, in the visitor's own language (see messages.rs).
+// Setting birthday to an empty value turns that off.
+
+use crate::clock::{Date, Timestamp, Weekday};
+use crate::messages::Messages;
+use crate::template::{Template, TemplateValues};
+use crate::Visitor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartOfDay {
+    Morning,
+    Afternoon,
+    Evening,
+    Night,
+}
+
+impl PartOfDay {
+    pub const ALL: [PartOfDay; 4] = [
+        PartOfDay::Morning,
+        PartOfDay::Afternoon,
+        PartOfDay::Evening,
+        PartOfDay::Night,
+    ];
+
+    pub fn at(local: Timestamp) -> Self {
+        match local.seconds_of_day() / 3600 {
+            5..=11 => PartOfDay::Morning,
+            12..=16 => PartOfDay::Afternoon,
+            17..=21 => PartOfDay::Evening,
+            _ => PartOfDay::Night,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PartOfDay::Morning => "morning",
+            PartOfDay::Afternoon => "afternoon",
+            PartOfDay::Evening => "evening",
+            PartOfDay::Night => "night",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        PartOfDay::ALL
+            .into_iter()
+            .find(|part| part.label() == label)
+    }
+}
+
+// Which rule a greeting came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Occasion {
+    Birthday,
+    Weekday(Weekday),
+    PartOfDay(PartOfDay),
+    Usual, // the visitor's stored greeting.
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum BirthdayGreeting {
+    Off,
+    #[default]
+    Standard, // the birthday_greeting message, in the visitor's language.
+    Custom(Template),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GreetingRules {
+    pub birthday: BirthdayGreeting,
+    pub weekdays: Vec<(Weekday, Template)>,
+    pub parts_of_day: Vec<(PartOfDay, Template)>,
+}
+
+// True when today is the visitor's birthday. People born on 29 February celebrate on the 28th
+// in years without one.
+pub fn is_birthday(visitor: &Visitor, today: Date) -> bool {
+    let Some(born) = visitor.birth_date else {
+        return false;
+    };
+    if (born.month, born.day) == (2, 29) && !Date::is_leap_year(today.year) {
+        return (today.month, today.day) == (2, 28);
+    }
+    (born.month, born.day) == (today.month, today.day)
+}
+
+impl GreetingRules {
+    // local is the time on the treehouse clock, see Timestamp::to_local.
+    // language is only used for the standard birthday greeting, the others are written in the config.
+    pub fn greeting_for(
+        &self,
+        visitor: &Visitor,
+        local: Timestamp,
+        values: &TemplateValues,
+        messages: &Messages,
+        language: &str,
+    ) -> (Occasion, String) {
+        let today = local.date();
+        let fill = |template: &Template| template.render(values);
+
+        if is_birthday(visitor, today) {
+            match &self.birthday {
+                BirthdayGreeting::Off => {}
+                BirthdayGreeting::Standard => {
+                    let greeting =
+                        messages.text(language, "birthday_greeting", &[("name", values.name)]);
+                    return (Occasion::Birthday, greeting);
+                }
+                BirthdayGreeting::Custom(template) => return (Occasion::Birthday, fill(template)),
+            }
+        }
+        let weekday = today.weekday();
+        if let Some((_, template)) = self.weekdays.iter().find(|(day, _)| *day == weekday) {
+            return (Occasion::Weekday(weekday), fill(template));
+        }
+        let part = PartOfDay::at(local);
+        if let Some((_, template)) = self.parts_of_day.iter().find(|(p, _)| *p == part) {
+            return (Occasion::PartOfDay(part), fill(template));
+        }
+        // Stored greetings are checked when they are saved, so this only falls back to the raw text
+        // for a visitor that never went through the registry.
+        let usual = match Template::parse(&visitor.greeting) {
+            Ok(template) => template.render(values),
+            Err(_) => visitor.greeting.clone(),
+        };
+        (Occasion::Usual, usual)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VisitorAction;
+
+    fn at(time: &str) -> Timestamp {
+        Timestamp::parse(time).expect("test times are valid")
+    }
+
+    fn template(text: &str) -> Template {
+        Template::parse(text).expect("test templates are valid")
+    }
+
+    fn born(year: i32, month: u32, day: u32) -> Visitor {
+        let date = Date::new(year, month, day);
+        Visitor::new("Bert", "Hi {name}", VisitorAction::Accept, date)
+    }
+
+    // 2026-10-18 is a Sunday.
+    fn rules() -> GreetingRules {
+        GreetingRules {
+            birthday: BirthdayGreeting::Custom(template("Cake for {name}!")),
+            weekdays: vec![(Weekday::Sunday, template("Lazy Sunday, {name}"))],
+            parts_of_day: vec![(PartOfDay::Morning, template("Morning, {name}"))],
+        }
+    }
+
+    fn greet(rules: &GreetingRules, visitor: &Visitor, local: &str) -> (Occasion, String) {
+        let messages = Messages::default();
+        let values = TemplateValues {
+            name: &visitor.name,
+            visits: 1,
+            last_visit: None,
+            never: "never",
+            note: None,
+        };
+        let language = messages.default_language();
+        rules.greeting_for(visitor, at(local), &values, &messages, language)
+    }
+
+    #[test]
+    fn the_most_specific_greeting_wins() {
+        let mut rules = rules();
+        let birthday = born(1980, 10, 18);
+        let other_day = born(1980, 5, 1);
+        let sunday_morning = "2026-10-18T09:00:00Z";
+
+        assert_eq!(
+            greet(&rules, &birthday, sunday_morning),
+            (Occasion::Birthday, "Cake for Bert!".to_string())
+        );
+        assert_eq!(
+            greet(&rules, &other_day, sunday_morning),
+            (
+                Occasion::Weekday(Weekday::Sunday),
+                "Lazy Sunday, Bert".to_string()
+            )
+        );
+        assert_eq!(
+            greet(&rules, &other_day, "2026-10-19T09:00:00Z"),
+            (
+                Occasion::PartOfDay(PartOfDay::Morning),
+                "Morning, Bert".to_string()
+            )
+        );
+        assert_eq!(
+            greet(&rules, &other_day, "2026-10-19T13:00:00Z"),
+            (Occasion::Usual, "Hi Bert".to_string())
+        );
+
+        // Turning birthdays off falls through to the next rule, the standard one is in the visitor's language.
+        rules.birthday = BirthdayGreeting::Off;
+        assert_eq!(
+            greet(&rules, &birthday, sunday_morning).0,
+            Occasion::Weekday(Weekday::Sunday)
+        );
+        rules.birthday = BirthdayGreeting::Standard;
+        assert_eq!(
+            greet(&rules, &birthday, sunday_morning),
+            (Occasion::Birthday, "Happy birthday, Bert!".to_string())
+        );
+    }
+
+    #[test]
+    fn leap_day_birthdays() {
+        let leap_day = born(2000, 2, 29);
+        let day = |year, month, day| Date::new(year, month, day).unwrap();
+        assert!(is_birthday(&leap_day, day(2028, 2, 29)));
+        assert!(!is_birthday(&leap_day, day(2028, 2, 28)));
+        assert!(is_birthday(&leap_day, day(2027, 2, 28)));
+        assert!(!is_birthday(&leap_day, day(2027, 3, 1)));
+        assert!(!is_birthday(&born(2000, 2, 28), day(2028, 2, 29)));
+        assert!(!is_birthday(
+            &Visitor::probationary("Aunt May", None),
+            day(2027, 2, 28)
+        ));
+    }
+
+    #[test]
+    fn parts_of_the_day() {
+        let part = |time: &str| PartOfDay::at(at(&format!("2026-10-18T{}Z", time)));
+        assert_eq!(part("04:59:59"), PartOfDay::Night);
+        assert_eq!(part("05:00:00"), PartOfDay::Morning);
+        assert_eq!(part("11:59:59"), PartOfDay::Morning);
+        assert_eq!(part("12:00:00"), PartOfDay::Afternoon);
+        assert_eq!(part("16:59:59"), PartOfDay::Afternoon);
+        assert_eq!(part("17:00:00"), PartOfDay::Evening);
+        assert_eq!(part("21:59:59"), PartOfDay::Evening);
+        assert_eq!(part("22:00:00"), PartOfDay::Night);
+        assert_eq!(part("00:00:00"), PartOfDay::Night);
+        assert_eq!(PartOfDay::from_label("evening"), Some(PartOfDay::Evening));
+        assert_eq!(PartOfDay::from_label("noon"), None);
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Stdin};
+use std::path::Path;
+
+// Anything that can hand the door a name: a person typing, a file, a test script or a badge reader.
+// The door loop only talks to this trait, so it doesn't care where the names come from.
+pub trait NameSource {
+    // Returns the next name with surrounding whitespace removed.
+    // Running out of names is reported as InputError::Eof rather than an empty string,
+    // so an empty line typed by a person still means "quit" and is not confused with the end of a file.
+    fn next_name(&mut self) -> Result<String, InputError>;
+
+    // Interactive sources have a person in front of them who needs to be prompted.
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    Eof,
+    Io(io::Error),
+    UnknownBadge(String),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputError::Eof => write!(f, "no more names"),
+            InputError::Io(error) => write!(f, "could not read a name: {}", error),
+            InputError::UnknownBadge(code) => write!(f, "badge {} is not registered", code),
+        }
+    }
+}
+
+impl std::error::Error for InputError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InputError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+// From lets the ? operator turn an io::Error into an InputError automatically.
+impl From<io::Error> for InputError {
+    fn from(error: io::Error) -> Self {
+        InputError::Io(error)
+    }
+}
+
+// Reads one name per line from anything that implements BufRead.
+// The type parameter R is filled in with Stdin, a file, or even a byte slice in tests.
+pub struct LineSource<R> {
+    reader: R,
+    interactive: bool,
+}
+
+impl<R: BufRead> LineSource<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            interactive: false,
+        }
+    }
+}
+
+impl LineSource<BufReader<Stdin>> {
+    pub fn stdin() -> Self {
+        Self {
+            reader: BufReader::new(io::stdin()),
+            interactive: true,
+        }
+    }
+}
+
+impl LineSource<BufReader<File>> {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> NameSource for LineSource<R> {
+    fn next_name(&mut self) -> Result<String, InputError> {
+        let mut line = String::new();
+        // read_line returns how many bytes it read, 0 means the end of the input was reached.
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(InputError::Eof);
+        }
+        Ok(line.trim().to_string())
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.interactive
+    }
+}
+
+// Plays back a fixed list of names, which makes a whole visit easy to script in a test.
+#[derive(Debug, Default)]
+pub struct ScriptedSource {
+    names: VecDeque<String>,
+}
+
+impl ScriptedSource {
+    // IntoIterator means this accepts an array, a Vec, or any other iterator of things that can become a String.
+    pub fn new<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            names: names.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl NameSource for ScriptedSource {
+    fn next_name(&mut self) -> Result<String, InputError> {
+        self.names
+            .pop_front()
+            .map(|name| name.trim().to_string())
+            .ok_or(InputError::Eof)
+    }
+}
+
+// A pretend badge reader. Each swipe produces a badge code from the inner source,
+// and the code is turned into the name it was issued to.
+pub struct BadgeReader<S> {
+    swipes: S,
+    badges: HashMap<String, String>,
+}
+
+impl<S: NameSource> BadgeReader<S> {
+    pub fn new(swipes: S) -> Self {
+        Self {
+            swipes,
+            badges: HashMap::new(),
+        }
+    }
+
+    pub fn issue(&mut self, code: &str, name: &str) {
+        self.badges
+            .insert(code.trim().to_string(), name.to_string());
+    }
+}
+
+impl<S: NameSource> NameSource for BadgeReader<S> {
+    fn next_name(&mut self) -> Result<String, InputError> {
+        let code = self.swipes.next_name()?;
+        // An empty swipe is passed through so it still means "quit", just like an empty line.
+        if code.is_empty() {
+            return Ok(code);
+        }
+        self.badges
+            .get(&code)
+            .cloned()
+            .ok_or(InputError::UnknownBadge(code))
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.swipes.is_interactive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripted_names_are_trimmed_and_run_out() {
+        let mut source = ScriptedSource::new([" Bert ", "", "Steve"]);
+        assert!(!source.is_interactive());
+        assert_eq!(source.next_name().unwrap(), "Bert");
+        assert_eq!(source.next_name().unwrap(), "");
+        assert_eq!(source.next_name().unwrap(), "Steve");
+        assert!(matches!(source.next_name(), Err(InputError::Eof)));
+    }
+
+    #[test]
+    fn lines_are_read_one_name_at_a_time() {
+        let mut source = LineSource::new("Bert\r\n  Aunt May\n".as_bytes());
+        assert_eq!(source.next_name().unwrap(), "Bert");
+        assert_eq!(source.next_name().unwrap(), "Aunt May");
+        assert!(matches!(source.next_name(), Err(InputError::Eof)));
+    }
+
+    #[test]
+    fn badges_are_turned_into_names() {
+        let mut reader = BadgeReader::new(ScriptedSource::new(["0042", "9999", ""]));
+        reader.issue("0042", "Bert");
+        assert_eq!(reader.next_name().unwrap(), "Bert");
+        assert!(matches!(
+            reader.next_name(),
+            Err(InputError::UnknownBadge(code)) if code == "9999"
+        ));
+        assert_eq!(reader.next_name().unwrap(), "");
+        assert!(matches!(reader.next_name(), Err(InputError::Eof)));
+    }
+}
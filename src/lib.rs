@@ -0,0 +1,34 @@
+// The library half of the crate. Anything marked pub here can be used by other tools with
+// `use rust_treehouse::...`, while src/main.rs is only the interactive front door built on top of it.
+
+pub mod access;
+pub mod admin;
+pub mod audit;
+pub mod batch;
+pub mod clock;
+pub mod config;
+pub mod decision;
+pub mod evacuation;
+pub mod fuzzy;
+pub mod greeting;
+pub mod input;
+pub mod messages;
+pub mod minors;
+pub mod names;
+pub mod occupancy;
+pub mod passes;
+pub mod probation;
+pub mod registry;
+pub mod serving;
+pub mod sponsors;
+pub mod storage;
+pub mod template;
+pub mod visit_log;
+pub mod visitor;
+
+// pub use re-exports the most used types so callers don't need to know which module they live in.
+pub use decision::{
+    AdmissionContext, AdmissionDecision, DecisionRenderer, Outcome, TerminalRenderer, Warning,
+};
+pub use registry::{Lookup, RegistryError, VisitorRegistry};
+pub use visitor::{Visitor, VisitorAction, VisitorId};
@@ -1,66 +1,104 @@
-use std::io::stdin;
-
-// The debug placeholders {:?} for raw printing, and {:#?} for pretty printing
-// can be used on any type that supports the Debug trait.
-// The Debug trait is added with a derive attribute.
-// Deriving requires that every member field in the structure supports the feature being derived.
-#[derive(Debug)]
-struct Visitor {
-    name: String,
-    action: VisitorAction,
-    age: i8, // 8 bit signed integer can hold from -128 to 127
-    greeting: String,
-}
-
-impl Visitor {
-    // impl implements functions for a struct, it is followed the name of the struct to implement.
-    // methods can access the struct contents. Associated functions, can't.
-
-    // new is an associated function that is a constructor as it returns Self.
-    fn new(name: &str, greeting: &str, action: VisitorAction, age: i8) -> Self {
-        // Self (with capital) refers to struct type.
-        // Note that not initialising all fields in a struct results in a compilation error
-        Self {
-            name: name.to_lowercase(), // to_lowercase() and to_string() convert str to String.
-            greeting: greeting.to_string(),
-            // if the data is in a variable with the same name as the structs field name
-            action, // the colon and value can be omitted. Rust will just use the variable of the same name.
-            age,
-        } // lack of semi-colon here is an implicit return.
-    }
-    fn greet_visitor(&self) {
-        // &self as a parameter means the method has access to the struct contents.
-        println!("{}", self.greeting); // self (lowercase) refers to the instance of the struct, not its type.
-
-        match &self.action {
-            VisitorAction::Accept => println!("Welcome to the tree house, {}", self.name),
-            VisitorAction::AcceptWithNote { note } => {
-                // if the enum option has data, its destructured with {}
-                println!("Welcome to the tree house, {}", self.name);
-                println!("{}", note); // destructured enum data is available in match scope by name.
-                if self.age < 21 {
-                    println!("Do not serve alcohol to {}", self.name)
-                }
-            } // this arm of match uses a scope block instead of a single expression.
-            VisitorAction::Probation => println!("{} is now a probationary member", self.name),
-            VisitorAction::Refuse => println!("Do not allow {} in!", self.name),
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+// Everything except the interactive loop lives in the library half of the crate, see src/lib.rs.
+use rust_treehouse::audit::{self, AuditLog};
+use rust_treehouse::clock::{format_duration, Clock, Date, FixedClock, SystemClock, Timestamp};
+use rust_treehouse::config::{self, Config};
+use rust_treehouse::evacuation::{self, MarkError, RollCall};
+use rust_treehouse::fuzzy::{self, FuzzyMatching};
+use rust_treehouse::input::{InputError, LineSource, NameSource};
+use rust_treehouse::messages::Messages;
+use rust_treehouse::names::display_name;
+use rust_treehouse::occupancy::{self, Occupancy, OccupancySettings, QueueOrder};
+use rust_treehouse::probation::{self, Progress, Transition};
+use rust_treehouse::serving::{self, Serving};
+use rust_treehouse::sponsors::{self, Sponsorship, VouchRefusal};
+use rust_treehouse::visit_log::{VisitFilter, VisitLog, VisitRecord};
+use rust_treehouse::{admin, batch};
+use rust_treehouse::{
+    storage, DecisionRenderer, Lookup, TerminalRenderer, Visitor, VisitorAction, VisitorId,
+    VisitorRegistry,
+};
+
+// The visitor list and settings live in this directory. Set TREEHOUSE_DIR to keep them somewhere else.
+const DEFAULT_DATA_DIR: &str = "treehouse-data";
+const VISITOR_FILE: &str = "visitors.txt";
+const CONFIG_FILE: &str = "treehouse.conf";
+const AUDIT_FILE: &str = "audit.log";
+const MESSAGES_DIR: &str = "messages";
+
+const USAGE: &str = "Usage:
+    rust-treehouse [--names <file>]                 run the front door
+    rust-treehouse check <file> [--enroll-unknown]  pre-screen a list of names
+    rust-treehouse add <name> [--born <YYYY-MM-DD>] [--language <code>] [--action <action>] [--note <text>] [--greeting <text>] [--alias <alias>]...
+    rust-treehouse remove <name>
+    rust-treehouse add-alias <name> <alias>
+    rust-treehouse remove-alias <name> <alias>
+    rust-treehouse set-action <name> <action> [--note <text>]
+    rust-treehouse set-note <name> <text>
+    rust-treehouse set-greeting <name> <text>
+    rust-treehouse set-born <name> <YYYY-MM-DD>
+    rust-treehouse set-language <name> <code>
+    rust-treehouse add-rule <name> allow|deny <schedule>
+    rust-treehouse remove-rule <name> allow|deny <schedule>
+    rust-treehouse add-group <name> <group>
+    rust-treehouse remove-group <name> <group>
+    rust-treehouse add-guardian <name> <guardian>
+    rust-treehouse remove-guardian <name> <guardian>
+    rust-treehouse issue-pass <member> <guest> [--from <YYYY-MM-DD>] [--until <YYYY-MM-DD>] [--uses <n>]
+    rust-treehouse approve <name> <member>
+    rust-treehouse list
+    rust-treehouse show <name>
+    rust-treehouse visits [--visitor <name>] [--from <YYYY-MM-DD>] [--to <YYYY-MM-DD>]
+    rust-treehouse stays [--visitor <name>] [--from <YYYY-MM-DD>] [--to <YYYY-MM-DD>]
+    rust-treehouse inside                           who is in the treehouse right now
+    rust-treehouse checkout <name>                  check out someone who left without passing the door
+    rust-treehouse probation                        review everybody on probation, promoting or refusing them when due
+    rust-treehouse queue                            who is waiting for a place inside
+    rust-treehouse unqueue <name>                   take someone off the waiting queue
+    rust-treehouse serve <name> [--drink <what>]     record a drink, refused for anyone under the legal age
+    rust-treehouse drinks [--visitor <name>] [--from <YYYY-MM-DD>] [--to <YYYY-MM-DD>]
+    rust-treehouse evacuate start|status|end        emergency roll call, the door stays closed until it ends
+    rust-treehouse evacuate account <name>...       mark people as safely out
+    rust-treehouse verify                           check the audit log has not been tampered with
+
+    <action> is one of accept, accept_with_note, refuse or probation.
+    Greetings may use {name}, {visits}, {last_visit} and {note}.
+    A <schedule> is days and an optional time, e.g. `weekdays 15:00-19:00` or `sat,sun`.
+    Set TREEHOUSE_NOW to a time like 2026-10-18T16:00:00Z to run any command at that time.";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect(); // skip the program name itself.
+
+    // One clock for every command, so TREEHOUSE_NOW moves all of them to the same moment.
+    let clock = door_clock();
+    let clock = clock.as_ref();
+
+    // as_slice lets match look inside the vector with slice patterns like [first, rest @ ..].
+    match args.as_slice() {
+        [command, rest @ ..] if command == "check" => run_check(rest, clock),
+        [command, rest @ ..] if command == "visits" => run_visits(rest, clock),
+        [command] if command == "verify" => run_verify(),
+        [command] if command == "inside" => run_inside(clock),
+        [command, rest @ ..] if command == "checkout" => run_checkout(rest, clock),
+        [command] if command == "queue" => run_queue(clock),
+        [command] if command == "probation" => run_probation(clock),
+        [command, rest @ ..] if command == "evacuate" => run_evacuate(rest, clock),
+        [command, rest @ ..] if command == "serve" => run_serve(rest, clock),
+        [command, rest @ ..] if command == "drinks" => run_drinks(rest, clock),
+        [command, rest @ ..] if command == "unqueue" => run_unqueue(rest, clock),
+        [command, rest @ ..] if command == "stays" => run_stays(rest, clock),
+        [command, rest @ ..] if admin::ADMIN_COMMANDS.contains(&command.as_str()) => {
+            run_admin(command, rest, clock)
         }
+        _ => run_door(&args, clock),
     }
 }
 
-// enums can derive functionality just like structs.
-#[derive(Debug)]
-enum VisitorAction {
-    // like struct declarations, enum declarations don't end with a ;
-    // Accept would be assigned with VisitorAction::Accept
-    Accept, // this is a simple enumeration option with no associated data.
-    //AcceptWithNote would be assigned with VistorAction::AcceptWithNote{note: "my note".to_string()};
-    AcceptWithNote { note: String }, // this enum option contains data.
-    Refuse,
-    Probation,
-}
-
-fn main() {
+fn run_door(args: &[String], clock: &dyn Clock) {
     // let visitor_list = ["bert", "steve", "fred"]; // this is an array of str (string literals)
     // str and String are different types. str are strings entered in code and generally unchanging.
     // String is a dynamic type that stores location, length, capacity and can be appended to and edited.
@@ -94,30 +132,72 @@ fn main() {
 
     // now the vistor struct contains an age field and a visitor action enum
 
-    let mut visitor_list = vec![
-        Visitor::new(
-            "Bert",
-            "Hello Bert, enjoy your treehouse.",
-            VisitorAction::Accept,
-            45,
-        ),
-        Visitor::new(
-            "steve",
-            "Hi Steve. Your milk is in the fridge.",
-            VisitorAction::AcceptWithNote {
-                note: String::from("Lactose-free milk is in the fridge"),
-            },
-            15,
-        ),
-        Visitor::new("fred", "Wow, who invited Fred?", VisitorAction::Refuse, 30),
-    ];
+    // The list is now loaded from disk, see load_or_exit.
+    let visitor_file = visitor_file_path();
+    let mut visitor_list = load_or_exit(&visitor_file, clock);
+
+    let config = load_config_or_exit();
+    // Until we know who is at the door, everything is said in the default language.
+    let messages = load_messages_or_exit(&config);
+    let default = messages.default_language();
+    let visit_log = VisitLog::new(audit_log());
+    let mut occupancy = load_occupancy_or_exit();
+    let mut sponsorships = load_sponsorships_or_exit();
+    // A larger capacity in the config may have made room for people who were waiting.
+    fill_from_queue(
+        &mut occupancy,
+        clock,
+        &visitor_list,
+        &config,
+        &messages,
+        &sponsorships,
+    );
+    let renderer = TerminalRenderer::new(&messages);
+    let mut source = name_source(args);
 
     loop {
         // this is a loop that runs until it breaks.
         // it will break if there is no input.
-        println!("Hello, what's your name? (Leave empty and press ENTER to quit)");
-        let name = what_is_your_name();
-        println!("Hello {}", name);
+        if source.is_interactive() {
+            println!("{}", messages.text(default, "ask_name", &[]));
+        }
+        let name = match what_is_your_name(source.as_mut()) {
+            Ok(name) => name,
+            Err(InputError::Eof) => break, // running out of names ends the night just like an empty name.
+            Err(error @ InputError::UnknownBadge(_)) => {
+                println!("{}", error);
+                continue; // continue skips the rest of this pass and goes back to the top of the loop.
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                break; // every change was saved as it was made, there is nothing left to save.
+            }
+        };
+        if name.is_empty() && !source.is_interactive() {
+            continue; // only a person at the keyboard can ask to quit, blank lines in a file are skipped.
+        }
+        // The list is read again for every arrival, an admin may have changed it from another terminal. The door
+        // saves it straight after each change it makes and not at the end, so it never writes back a stale copy.
+        visitor_list = load_or_exit(&visitor_file, clock);
+        // So is the audit log behind who is inside and who vouched for whom. `checkout` and `evacuate end`
+        // in another terminal write to it too, and a copy from the start of the night would miss that.
+        occupancy = load_occupancy_or_exit();
+        sponsorships = load_sponsorships_or_exit();
+        // An evacuation can be started from another terminal too. The door is closed then, but not to people going out.
+        if !name.is_empty() {
+            if let Some(roll_call) = roll_call_or_exit() {
+                leave_during_evacuation(
+                    roll_call,
+                    &mut occupancy,
+                    clock,
+                    &visitor_list,
+                    &messages,
+                    &name,
+                );
+                continue;
+            }
+        }
+        println!("{}", messages.text(default, "hello", &[("name", &name)]));
         println!("{:?}", name); // this is a debug print, the {} place holder has been change to the debug placeholder
 
         // When the array was of str this was enough to search for a name.
@@ -129,25 +209,86 @@ fn main() {
         // }
 
         // Now it is an array of struct, need to search it with iterators.
-        // Iterators can do a lot, they are designed around function chaining.
-        // Each iterator step works as a building block to massage the data from the previous step into what you need.
-        // iterators are very fast, often faster than writing loops as the compiler can be certain you arent
-        // doing anything dangerous like trying to read beyond the end of an array so it can make many optimisations.
-
-        // note that the following iterator code supports a visitor_list is of type array and of type vector.
-
-        let known_visitor = visitor_list
-            .iter() // create an iterator that contains all the data in visitor_list
-            .find(|visitor| visitor.name == name); // find runs a closure. If the statement is true, it returns the matching value.
-                                                   // Closures are used a lot on Rust. Closures capture data from the scope in which they are called.
-                                                   // the matching values are stored in known_visitor.
-                                                   // known_visitor is of type Option because it might contain a visitor or it might not.
-                                                   // Options are enums that have two possible values Some(x) and None.
-                                                   // There are lots of ways to interact with options, but for now can use match().
+        // The search itself moved into VisitorRegistry::lookup so other tools can share it.
+        // Lookup is an enum like Option, but with a third possibility: two people can share a name.
+        // There are lots of ways to interact with enums, but for now can use match().
+        let known_visitor = match visitor_list.lookup(&name) {
+            Lookup::Found(visitor) => Some(visitor),
+            Lookup::Ambiguous(visitors) => {
+                match which_one_are_you(source.as_mut(), &messages, &name, &visitors) {
+                    Some(visitor) => Some(visitor),
+                    None => {
+                        let sorry = messages.text(default, "which_one_failed", &[("name", &name)]);
+                        println!("{}", sorry);
+                        log_visit(&visit_log, clock, &name, None, false);
+                        continue;
+                    }
+                }
+            }
+            // Before treating them as a stranger, check whether they just mistyped someone's name.
+            Lookup::NotFound => did_you_mean(
+                source.as_mut(),
+                &messages,
+                &visitor_list,
+                &name,
+                config.fuzzy,
+            ),
+        };
+        // known_visitor is of type Option because it might contain a visitor or it might not.
+        // Options are enums that have two possible values Some(x) and None.
 
         match known_visitor {
             // match is given an option
-            Some(visitor) => visitor.greet_visitor(), // for some a fat arrow => denotes the code to execute if there is some match
+            // Someone who is already inside and comes back to the door is on their way out.
+            Some(visitor) if occupancy.is_inside(visitor.id) => {
+                check_out(&mut occupancy, clock, visitor, &messages);
+                fill_from_queue(
+                    &mut occupancy,
+                    clock,
+                    &visitor_list,
+                    &config,
+                    &messages,
+                    &sponsorships,
+                );
+            }
+            Some(visitor) => {
+                // for some a fat arrow => denotes the code to execute if there is some match
+                let id = visitor.id;
+                // Probation may be over by now, which changes what the decision below will be.
+                if let Some(transition) =
+                    review_probation(&mut visitor_list, id, &config, clock.now(), &occupancy)
+                {
+                    save_or_exit(&visitor_file, &visitor_list);
+                    let visitor = visitor_list.get(id).expect("reviewed visitors exist");
+                    let language = messages.language_for(visitor.language.as_deref());
+                    let name = [("name", visitor.name.as_str())];
+                    match transition {
+                        Transition::Promoted(_) => {
+                            println!("{}", messages.text(language, "probation_promoted", &name))
+                        }
+                        Transition::Expired(_) => {
+                            println!("{}", messages.text(language, "probation_expired", &name))
+                        }
+                        Transition::Started | Transition::Ended => {}
+                    }
+                }
+                let visitor = visitor_list.get(id).expect("reviewed visitors exist");
+                let decision = visitor.admission_decision(&config.admission_context(
+                    clock.now(),
+                    &occupancy,
+                    &messages,
+                    &sponsorships,
+                ));
+                print!("{}", renderer.render(&decision));
+                // The sponsor has now heard about their guests, whatever happens next, so they aren't told again.
+                for sponsorship in &decision.vouched_for {
+                    audit_or_exit(&sponsorship.told_entry(clock.now()));
+                }
+                log_visit(&visit_log, clock, &name, Some(visitor), false);
+                if decision.outcome.lets_in() {
+                    admit(&mut occupancy, clock, config.occupancy, visitor, &messages);
+                }
+            }
             None => {
                 // None executes => if the option has no data.
                 if name.is_empty() {
@@ -155,35 +296,1157 @@ fn main() {
                     // is_empty is more efficient than checking name.len() == 0, which also works.
                     break; // break immediately jumps to the end of the loop.
                 } else {
-                    println!("{} is not on the visitor list.", name);
-                    visitor_list.push(Visitor::new(
+                    println!(
+                        "{}",
+                        messages.text(default, "not_on_list", &[("name", &name)])
+                    );
+                    let sponsor = who_vouches_for_you(
+                        source.as_mut(),
+                        &messages,
+                        &visitor_list,
+                        &config,
+                        clock.now(),
+                    );
+                    if sponsor.is_none() && config.sponsors.required {
+                        let sorry = messages.text(default, "sponsor_required", &[("name", &name)]);
+                        println!("{}", sorry);
+                        log_visit(&visit_log, clock, &name, None, false);
+                        continue;
+                    }
+                    let mut newcomer = Visitor::probationary(
                         &name,
-                        "New friend",
-                        VisitorAction::Probation,
-                        0,
+                        when_were_you_born(
+                            source.as_mut(),
+                            &messages,
+                            config.local_date(clock.now()),
+                        ),
+                    );
+                    // Answering the questions takes a while, so pick up any changes made in the meantime.
+                    visitor_list = load_or_exit(&visitor_file, clock);
+                    occupancy = load_occupancy_or_exit();
+                    newcomer.sponsor =
+                        sponsor.filter(|sponsor| visitor_list.get(*sponsor).is_some());
+                    newcomer.probation_since = Some(clock.now());
+                    // A new visitor has no aliases to clash, but the ids can run out.
+                    let id = match visitor_list.add(newcomer) {
+                        Ok(id) => id,
+                        Err(error) => {
+                            eprintln!("{}", error);
+                            log_visit(&visit_log, clock, &name, None, false);
+                            continue;
+                        }
+                    };
+                    save_or_exit(&visitor_file, &visitor_list);
+                    let visitor = visitor_list.get(id).expect("visitor was just added");
+                    audit_or_exit(&probation::entry(clock.now(), visitor, Transition::Started));
+                    // The vouch goes in the audit log, which is where the sponsor hears about it from.
+                    if let Some(sponsor) = sponsor.and_then(|sponsor| visitor_list.get(sponsor)) {
+                        let sponsorship = Sponsorship::new(clock.now(), sponsor, visitor);
+                        audit_or_exit(&sponsorship.to_record());
+                        sponsorships.push(sponsorship);
+                    }
+                    // New members get the same checks as everybody else, so a minor can still be refused.
+                    let decision = visitor.admission_decision(&config.admission_context(
+                        clock.now(),
+                        &occupancy,
+                        &messages,
+                        &sponsorships,
                     ));
+                    print!("{}", renderer.render(&decision));
+                    log_visit(&visit_log, clock, &name, Some(visitor), true);
+                    // probationary members are let in too, unless a rule says otherwise.
+                    if decision.outcome.lets_in() {
+                        admit(&mut occupancy, clock, config.occupancy, visitor, &messages);
+                    }
+                }
+            }
+        }
+    }
+    println!("{}", messages.text(default, "final_list", &[]));
+    println!("{:#?}", visitor_list.as_slice());
+}
+
+// Checks every name in a file without letting anyone in. The registry is only touched with --enroll-unknown.
+fn run_check(args: &[String], clock: &dyn Clock) {
+    let (path, enroll_unknown) = match args {
+        [path] => (path, false),
+        [path, flag] if flag == "--enroll-unknown" => (path, true),
+        _ => usage_error(),
+    };
+
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("Could not read {}: {}", path, error);
+            process::exit(1);
+        }
+    };
+
+    let visitor_file = visitor_file_path();
+    let mut visitor_list = load_or_exit(&visitor_file, clock);
+    let entries = batch::check_names(
+        &mut visitor_list,
+        &batch::read_names(&text),
+        enroll_unknown,
+        load_config_or_exit().fuzzy,
+        clock.now(),
+    );
+    print!("{}", batch::format_csv(&entries));
+
+    let enrolled: Vec<String> = entries
+        .iter()
+        .filter(|entry| entry.enrolled)
+        .map(|entry| {
+            format!(
+                "enrolled {} (#{})",
+                entry.name,
+                entry.id.unwrap_or_default()
+            )
+        })
+        .collect();
+    if !enrolled.is_empty() {
+        save_or_exit(&visitor_file, &visitor_list);
+        audit_or_exit(&audit::admin_entry(clock.now(), "check", &enrolled));
+        // Their probation started just like it does at the door.
+        for id in entries
+            .iter()
+            .filter(|entry| entry.enrolled)
+            .filter_map(|entry| entry.id)
+        {
+            let visitor = visitor_list
+                .get(id)
+                .expect("enrolled visitors were just added");
+            audit_or_exit(&probation::entry(clock.now(), visitor, Transition::Started));
+        }
+    }
+}
+
+fn run_admin(name: &str, args: &[String], clock: &dyn Clock) {
+    let config = load_config_or_exit();
+    let today = config.local_date(clock.now());
+    let command = match admin::parse_command(name, args, today) {
+        Ok(command) => command,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(2);
+        }
+    };
+
+    let visitor_file = visitor_file_path();
+    let mut visitor_list = load_or_exit(&visitor_file, clock);
+    match admin::run_command(&mut visitor_list, command, today, &config) {
+        Ok(report) => {
+            for line in &report.lines {
+                println!("{}", line);
+            }
+            if report.changed {
+                save_or_exit(&visitor_file, &visitor_list);
+                audit_or_exit(&audit::admin_entry(clock.now(), name, &report.lines));
+                // Probation started or ended by hand gets its own entry, like a review would.
+                if let Some((id, transition)) = report.probation {
+                    let visitor = visitor_list.get(id).expect("the command just changed them");
+                    audit_or_exit(&probation::entry(clock.now(), visitor, transition));
+                }
+            }
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    }
+}
+
+// Shows the visit log, optionally only for one visitor and between two dates (both included).
+fn run_visits(args: &[String], clock: &dyn Clock) {
+    let visitor_list = load_or_exit(&visitor_file_path(), clock);
+    let filter = visit_filter_or_exit(args, &visitor_list);
+
+    let visits = match VisitLog::new(audit_log()).query(&filter) {
+        Ok(visits) => visits,
+        Err(error) => {
+            eprintln!("Could not read the audit log: {}", error);
+            process::exit(1);
+        }
+    };
+    for visit in &visits {
+        let who = match visit.visitor {
+            Some(id) => match visitor_list.get(id) {
+                Some(visitor) => format!("{} (#{})", visitor.name, id),
+                None => format!("#{} (removed)", id),
+            },
+            None => "-".to_string(),
+        };
+        let action = visit.action.as_ref().map_or("-", |action| action.label());
+        println!(
+            "{}  {:<16} {:<20} {}{}",
+            visit.time,
+            visit.entered_name,
+            who,
+            action,
+            if visit.enrolled { " (enrolled)" } else { "" }
+        );
+    }
+}
+
+// Lists everybody inside right now and how long they have been there.
+// Minors who are still inside after their allowed hours are marked, see minors.rs.
+fn run_inside(clock: &dyn Clock) {
+    let occupancy = load_occupancy_or_exit();
+    let visitor_list = load_or_exit(&visitor_file_path(), clock);
+    let config = load_config_or_exit();
+    let now = clock.now();
+    let local = now.to_local(config.utc_offset);
+    let past_curfew = |id: VisitorId| {
+        visitor_list.get(id).is_some_and(|visitor| {
+            config.minors.is_minor(visitor, local) == Some(true)
+                && !config.minors.within_hours(local)
+        })
+    };
+    match config.occupancy.capacity {
+        Some(capacity) => println!("{} of {} inside", occupancy.count_inside(), capacity),
+        None => println!("{} inside", occupancy.count_inside()),
+    }
+    for stay in occupancy.inside() {
+        println!(
+            "{:<20} since {}  ({}){}",
+            format!("{} (#{})", stay.name, stay.visitor),
+            stay.arrived,
+            format_duration(stay.duration(now)),
+            if past_curfew(stay.visitor) {
+                "  PAST CURFEW"
+            } else {
+                ""
+            }
+        );
+    }
+    if !occupancy.waiting().is_empty() {
+        println!(
+            "{} waiting, see `rust-treehouse queue`",
+            occupancy.waiting().len()
+        );
+    }
+}
+
+// Lets staff check out someone who left without going past the door.
+fn run_checkout(args: &[String], clock: &dyn Clock) {
+    let [name] = args else { usage_error() };
+    let visitor_list = load_or_exit(&visitor_file_path(), clock);
+    let id = match visitor_list.resolve(name) {
+        Ok(id) => id,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+    let visitor = visitor_list
+        .get(id)
+        .expect("resolve only returns ids that exist");
+    let mut occupancy = load_occupancy_or_exit();
+    if !occupancy.is_inside(id) {
+        eprintln!("{} (#{}) is not inside", visitor.name, id);
+        process::exit(1);
+    }
+    let config = load_config_or_exit();
+    let messages = load_messages_or_exit(&config);
+    check_out(&mut occupancy, clock, visitor, &messages);
+    let sponsorships = load_sponsorships_or_exit();
+    fill_from_queue(
+        &mut occupancy,
+        clock,
+        &visitor_list,
+        &config,
+        &messages,
+        &sponsorships,
+    );
+}
+
+// Shows the waiting queue, next in line first.
+fn run_queue(clock: &dyn Clock) {
+    let occupancy = load_occupancy_or_exit();
+    let settings = load_config_or_exit().occupancy;
+    let now = clock.now();
+    println!("{} waiting", occupancy.waiting().len());
+    for (index, waiting) in occupancy.waiting().iter().enumerate() {
+        let priority = match settings.queue_order {
+            QueueOrder::Fifo => String::new(),
+            QueueOrder::MembersFirst => format!("  priority {}", waiting.priority),
+        };
+        println!(
+            "{:>3}) {:<20} since {}  ({}){}",
+            index + 1,
+            format!("{} (#{})", waiting.name, waiting.visitor),
+            waiting.since,
+            format_duration(now.0 - waiting.since.0),
+            priority
+        );
+    }
+}
+
+// Takes someone off the waiting queue, e.g. because they went home.
+fn run_unqueue(args: &[String], clock: &dyn Clock) {
+    let [name] = args else { usage_error() };
+    let visitor_list = load_or_exit(&visitor_file_path(), clock);
+    let id = visitor_list.resolve(name).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+    let mut occupancy = load_occupancy_or_exit();
+    match occupancy.dequeue(id) {
+        Some(waiting) => {
+            audit_or_exit(&occupancy::unqueued_entry(clock.now(), id, &waiting.name));
+            println!("{} (#{}) is no longer waiting", waiting.name, id);
+        }
+        None => {
+            eprintln!("#{} is not in the queue", id);
+            process::exit(1);
+        }
+    }
+}
+
+// Every stay with how long it lasted. Filters the same way as visits, by arrival time.
+fn run_stays(args: &[String], clock: &dyn Clock) {
+    let visitor_list = load_or_exit(&visitor_file_path(), clock);
+    let filter = visit_filter_or_exit(args, &visitor_list);
+    let occupancy = load_occupancy_or_exit();
+    let now = clock.now();
+    for stay in occupancy.stays() {
+        if !filter.includes(Some(stay.visitor), stay.arrived) {
+            continue;
+        }
+        let left = match stay.left {
+            Some(left) => left.to_string(),
+            None => "still inside".to_string(),
+        };
+        println!(
+            "{}  {:<20}  {:<20}  {}",
+            stay.arrived,
+            left,
+            format!("{} (#{})", stay.name, stay.visitor),
+            format_duration(stay.duration(now))
+        );
+    }
+}
+
+// --visitor, --from and --to, shared by the visits and stays commands.
+fn visit_filter_or_exit(args: &[String], visitor_list: &VisitorRegistry) -> VisitFilter {
+    let mut filter = VisitFilter::default();
+
+    // chunks(2) walks the arguments two at a time, a flag and its value.
+    for pair in args.chunks(2) {
+        let [flag, value] = pair else { usage_error() };
+        let date = || Date::parse(value).unwrap_or_else(|| usage_error());
+        match flag.as_str() {
+            "--visitor" => match visitor_list.resolve(value) {
+                Ok(id) => filter.visitor = Some(id),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    process::exit(1);
+                }
+            },
+            "--from" => filter.from = Some(date().start()),
+            // --to includes the whole of that day, so the filter stops at the start of the next one.
+            "--to" => filter.until = Some(Date::from_days(date().days() + 1).start()),
+            _ => usage_error(),
+        }
+    }
+    filter
+}
+
+fn load_occupancy_or_exit() -> Occupancy {
+    let audit_log = audit_log();
+    let entries = audit_log.entries().unwrap_or_else(|error| {
+        eprintln!("Could not read the audit log: {}", error);
+        process::exit(1);
+    });
+    Occupancy::from_entries(&entries).unwrap_or_else(|error| {
+        eprintln!(
+            "Could not read the audit log: {}",
+            error.in_file(audit_log.path())
+        );
+        process::exit(1);
+    })
+}
+
+// Moves a visitor on probation along when it is due and writes the change to the audit log.
+// The caller saves the visitor list.
+fn review_probation(
+    visitor_list: &mut VisitorRegistry,
+    id: VisitorId,
+    config: &Config,
+    now: Timestamp,
+    occupancy: &Occupancy,
+) -> Option<Transition> {
+    let visitor = visitor_list.get_mut(id)?;
+    let transition = config.probation.review(visitor, now, occupancy)?;
+    audit_or_exit(&probation::entry(now, visitor, transition));
+    probation::apply(visitor, transition, now);
+    Some(transition)
+}
+
+// Shows how far everybody on probation has got, and promotes or refuses those who are due.
+// Visitors who never come back to the door are only reviewed here, so their probation can still run out.
+fn run_probation(clock: &dyn Clock) {
+    let visitor_file = visitor_file_path();
+    let mut visitor_list = load_or_exit(&visitor_file, clock);
+    let occupancy = load_occupancy_or_exit();
+    let config = load_config_or_exit();
+    let now = clock.now();
+    let policy = config.probation;
+    // "2/3" is two visits of the three it takes, a plain "2" means there is no such criterion.
+    let of = |got: u32, needed: Option<u32>| match needed {
+        Some(needed) => format!("{}/{}", got, needed),
+        None => got.to_string(),
+    };
+
+    let ids: Vec<VisitorId> = visitor_list
+        .iter()
+        .filter(|visitor| visitor.action == VisitorAction::Probation)
+        .map(|visitor| visitor.id)
+        .collect();
+    println!(
+        "{:>4}  {:<16} {:<20}  {:>6}  {:>6}  {:>9}",
+        "ID", "NAME", "SINCE", "VISITS", "DAYS", "APPROVALS"
+    );
+    let mut changes = Vec::new();
+    for id in ids {
+        let transition = review_probation(&mut visitor_list, id, &config, now, &occupancy);
+        let visitor = visitor_list.get(id).expect("ids were taken from the list");
+        let progress = match transition {
+            Some(Transition::Promoted(progress) | Transition::Expired(progress)) => Some(progress),
+            _ => Progress::of(visitor, now, &occupancy),
+        };
+        if let Some(progress) = progress {
+            println!(
+                "{:>4}  {:<16} {:<20}  {:>6}  {:>6}  {:>9}",
+                id,
+                visitor.name,
+                progress.since.to_string(),
+                of(progress.visits, policy.visits),
+                of(progress.days, policy.days),
+                of(progress.approvals, policy.approvals)
+            );
+        }
+        if let Some(transition) = transition {
+            changes.push(format!(
+                "{} (#{}): probation {}",
+                visitor.name,
+                id,
+                transition.label()
+            ));
+        }
+    }
+    for change in &changes {
+        println!("{}", change);
+    }
+    if !changes.is_empty() {
+        save_or_exit(&visitor_file, &visitor_list);
+    }
+}
+
+// Every vouch so far, see sponsors.rs.
+fn load_sponsorships_or_exit() -> Vec<Sponsorship> {
+    let audit_log = audit_log();
+    let entries = audit_log.entries().unwrap_or_else(|error| {
+        eprintln!("Could not read the audit log: {}", error);
+        process::exit(1);
+    });
+    Sponsorship::from_entries(&entries).unwrap_or_else(|error| {
+        eprintln!(
+            "Could not read the audit log: {}",
+            error.in_file(audit_log.path())
+        );
+        process::exit(1);
+    })
+}
+
+fn check_in(occupancy: &mut Occupancy, clock: &dyn Clock, visitor: VisitorId, name: &str) {
+    let now = clock.now();
+    audit_or_exit(&occupancy::checkin_entry(now, visitor, name));
+    occupancy.check_in(visitor, name, now);
+}
+
+// Tells someone in the queue how many people are in front of them.
+fn print_queue_place(messages: &Messages, language: &str, position: usize) {
+    let ahead = position.saturating_sub(1) as u64;
+    println!("{}", messages.plural(language, "queue_ahead", ahead, &[]));
+}
+
+// Lets an admitted visitor in, or puts them in the queue when the treehouse is full.
+fn admit(
+    occupancy: &mut Occupancy,
+    clock: &dyn Clock,
+    settings: OccupancySettings,
+    visitor: &Visitor,
+    messages: &Messages,
+) {
+    let language = messages.language_for(visitor.language.as_deref());
+    let name = [("name", visitor.name.as_str())];
+    if let Some(position) = occupancy.queue_position(visitor.id) {
+        println!("{}", messages.text(language, "queue_still_full", &name));
+        print_queue_place(messages, language, position);
+    } else if occupancy.has_room(settings) {
+        check_in(occupancy, clock, visitor.id, &visitor.name);
+    } else {
+        let now = clock.now();
+        let priority = settings.queue_order.priority(&visitor.action);
+        audit_or_exit(&occupancy::queued_entry(
+            now,
+            visitor.id,
+            &visitor.name,
+            priority,
+        ));
+        let position = occupancy.enqueue(visitor.id, &visitor.name, priority, now);
+        println!("{}", messages.text(language, "queue_full", &name));
+        print_queue_place(messages, language, position);
+    }
+}
+
+// Lets in whoever is next in the queue for as long as there is room.
+// Time has passed since they were queued, so the door decides again for each of them. Anybody who can't
+// come in any more, e.g. because their hours are over or their pass ran out, is taken off the queue instead.
+// This is said to whoever looks after the door, so it is in the default language.
+fn fill_from_queue(
+    occupancy: &mut Occupancy,
+    clock: &dyn Clock,
+    visitor_list: &VisitorRegistry,
+    config: &Config,
+    messages: &Messages,
+    sponsorships: &[Sponsorship],
+) {
+    if roll_call_or_exit().is_some() {
+        return; // nobody is let in during an evacuation, the queue waits until it is over.
+    }
+    // Read the queue again rather than trust the caller's copy. Somebody may have been let in, checked out
+    // or taken off the queue from another terminal since, and nobody should be let in twice.
+    *occupancy = load_occupancy_or_exit();
+    let language = messages.default_language();
+    while occupancy.has_room(config.occupancy) {
+        // cloned copies the first entry so the queue can be changed while we still use it.
+        let Some(next) = occupancy.waiting().first().cloned() else {
+            break;
+        };
+        let now = clock.now();
+        // Somebody removed from the list while they were waiting can't be let in either.
+        let lets_in = visitor_list.get(next.visitor).is_some_and(|visitor| {
+            visitor
+                .admission_decision(&config.admission_context(
+                    now,
+                    occupancy,
+                    messages,
+                    sponsorships,
+                ))
+                .outcome
+                .lets_in()
+        });
+        let name = ("name", next.name.as_str());
+        if lets_in {
+            check_in(occupancy, clock, next.visitor, &next.name);
+            let waited = format_duration(now.0 - next.since.0);
+            let text = messages.text(language, "come_in_now", &[name, ("duration", &waited)]);
+            println!("{}", text);
+        } else {
+            audit_or_exit(&occupancy::unqueued_entry(now, next.visitor, &next.name));
+            occupancy.dequeue(next.visitor);
+            println!(
+                "{}",
+                messages.text(language, "turned_away_from_queue", &[name])
+            );
+        }
+    }
+}
+
+fn check_out(occupancy: &mut Occupancy, clock: &dyn Clock, visitor: &Visitor, messages: &Messages) {
+    let now = clock.now();
+    audit_or_exit(&occupancy::checkout_entry(now, visitor.id, &visitor.name));
+    if let Some(stay) = occupancy.check_out(visitor.id, now) {
+        let language = messages.language_for(visitor.language.as_deref());
+        let duration = format_duration(stay.duration(now));
+        let values = [("name", visitor.name.as_str()), ("duration", &duration)];
+        println!("{}", messages.text(language, "goodbye", &values));
+    }
+}
+
+// Lets somebody who is inside out during an evacuation. Going out past the door gets them to safety, so they
+// are marked on the roll call too. Everybody else is told the door is closed.
+// A name shared by several visitors is narrowed down to the ones inside, there is no time to ask which one.
+fn leave_during_evacuation(
+    mut roll_call: RollCall,
+    occupancy: &mut Occupancy,
+    clock: &dyn Clock,
+    visitor_list: &VisitorRegistry,
+    messages: &Messages,
+    name: &str,
+) {
+    let candidates = match visitor_list.lookup(name) {
+        Lookup::Found(visitor) => vec![visitor],
+        Lookup::Ambiguous(visitors) => visitors,
+        Lookup::NotFound => Vec::new(),
+    };
+    let leaving: Vec<&Visitor> = candidates
+        .into_iter()
+        .filter(|visitor| occupancy.is_inside(visitor.id))
+        .collect();
+    let [visitor] = leaving.as_slice() else {
+        let language = messages.default_language();
+        println!("{}", messages.text(language, "evacuating", &[]));
+        return;
+    };
+    check_out(occupancy, clock, visitor, messages);
+    let now = clock.now();
+    if roll_call.mark(visitor.id, now).is_ok() {
+        audit_or_exit(&evacuation::accounted_entry(now, visitor.id, &visitor.name));
+    }
+}
+
+// Records a drink for a visitor, unless the serving policy says they may not have one.
+fn run_serve(args: &[String], clock: &dyn Clock) {
+    let (name, drink) = match args {
+        [name] => (name, "drink"),
+        [name, flag, drink] if flag == "--drink" => (name, drink.as_str()),
+        _ => usage_error(),
+    };
+    let visitor_list = load_or_exit(&visitor_file_path(), clock);
+    let id = visitor_list.resolve(name).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+    let visitor = visitor_list
+        .get(id)
+        .expect("resolve only returns ids that exist");
+
+    let config = load_config_or_exit();
+    let now = clock.now();
+    // The door decides again, so somebody refused or out of hours since they came in isn't served.
+    let occupancy = load_occupancy_or_exit();
+    let messages = load_messages_or_exit(&config);
+    let sponsorships = load_sponsorships_or_exit();
+    let decision = visitor.admission_decision(&config.admission_context(
+        now,
+        &occupancy,
+        &messages,
+        &sponsorships,
+    ));
+    if let Err(reason) = serving::check_servable(occupancy.is_inside(id), decision.outcome) {
+        eprintln!(
+            "Do not serve {} (#{}), {}. No drink was recorded.",
+            visitor.name, id, reason
+        );
+        process::exit(1);
+    }
+    let serving = Serving::attempt(&config.serving, visitor, drink, now, config.local_date(now));
+    audit_or_exit(&serving.to_record());
+    match &serving.refused {
+        None => println!("Served {} to {} (#{})", drink, visitor.name, id),
+        Some(reason) => {
+            eprintln!(
+                "Do not serve alcohol to {} (#{}), {}. No drink was recorded.",
+                visitor.name, id, reason
+            );
+            process::exit(1);
+        }
+    }
+}
+
+// The serving log, filtered the same way as visits.
+fn run_drinks(args: &[String], clock: &dyn Clock) {
+    let visitor_list = load_or_exit(&visitor_file_path(), clock);
+    let filter = visit_filter_or_exit(args, &visitor_list);
+    let audit_log = audit_log();
+    let entries = audit_log.entries().unwrap_or_else(|error| {
+        eprintln!("Could not read the audit log: {}", error);
+        process::exit(1);
+    });
+    for serving in entries.iter().filter_map(Serving::from_record) {
+        let serving = serving.unwrap_or_else(|error| {
+            eprintln!(
+                "Could not read the audit log: {}",
+                error.in_file(audit_log.path())
+            );
+            process::exit(1);
+        });
+        if !filter.includes(Some(serving.visitor), serving.time) {
+            continue;
+        }
+        let outcome = match &serving.refused {
+            None => "served".to_string(),
+            Some(reason) => format!("REFUSED ({})", reason),
+        };
+        println!(
+            "{}  {:<20} {:<16} {}",
+            serving.time,
+            format!("{} (#{})", serving.name, serving.visitor),
+            serving.drink,
+            outcome
+        );
+    }
+}
+
+// Emergency evacuation: start, status, account <name>... and end. See evacuation.rs.
+fn run_evacuate(args: &[String], clock: &dyn Clock) {
+    let roll_call = roll_call_or_exit();
+    match (args, roll_call) {
+        ([command], None) if command == "start" => {
+            let roll_call = RollCall::start(&load_occupancy_or_exit(), clock.now());
+            audit_or_exit(&evacuation::start_entry(roll_call.started));
+            println!(
+                "EVACUATION STARTED at {}. The door is closed.",
+                roll_call.started
+            );
+            print_roll_call(&roll_call);
+        }
+        ([command], Some(roll_call)) if command == "status" => print_roll_call(&roll_call),
+        ([command, names @ ..], Some(mut roll_call))
+            if command == "account" && !names.is_empty() =>
+        {
+            let visitor_list = load_or_exit(&visitor_file_path(), clock);
+            for name in names {
+                // The roll is checked first so people removed from the list since can still be marked by #id.
+                let id = match roll_call
+                    .roll
+                    .iter()
+                    .find(|entry| format!("#{}", entry.visitor) == *name)
+                {
+                    Some(entry) => entry.visitor,
+                    None => match visitor_list.resolve(name) {
+                        Ok(id) => id,
+                        Err(error) => {
+                            eprintln!("{}", error);
+                            continue;
+                        }
+                    },
+                };
+                let now = clock.now();
+                match roll_call.mark(id, now) {
+                    Ok(entry) => {
+                        audit_or_exit(&evacuation::accounted_entry(now, id, &entry.name));
+                        println!("{} (#{}) is accounted for", entry.name, id);
+                    }
+                    Err(MarkError::NotOnRoll) => {
+                        eprintln!("{} was not inside when the evacuation started", name)
+                    }
+                    Err(MarkError::AlreadyAccounted(time)) => {
+                        eprintln!("{} was already accounted for at {}", name, time)
+                    }
                 }
             }
+            let missing = roll_call.missing().count();
+            println!("{} still missing", missing);
         }
+        ([command], Some(roll_call)) if command == "end" => {
+            let now = clock.now();
+            audit_or_exit(&evacuation::end_entry(now));
+            // Everybody who got out has left. Anybody missing stays checked in, nobody saw them go.
+            // Those who went out past the door were checked out there already.
+            let mut occupancy = load_occupancy_or_exit();
+            for entry in roll_call.accounted() {
+                if !occupancy.is_inside(entry.visitor) {
+                    continue;
+                }
+                audit_or_exit(&occupancy::checkout_entry(now, entry.visitor, &entry.name));
+                occupancy.check_out(entry.visitor, now);
+            }
+            println!("EVACUATION ENDED at {}", now);
+            println!(
+                "Started {}, {} of {} accounted for.",
+                roll_call.started,
+                roll_call.accounted().count(),
+                roll_call.roll.len()
+            );
+            let missing: Vec<_> = roll_call.missing().collect();
+            if missing.is_empty() {
+                println!("Nobody is missing.");
+            } else {
+                println!("STILL MISSING:");
+                for entry in missing {
+                    println!("  {} (#{})", entry.name, entry.visitor);
+                }
+                process::exit(1);
+            }
+        }
+        ([command], Some(_)) if command == "start" => {
+            eprintln!("An evacuation is already in progress, see `rust-treehouse evacuate status`");
+            process::exit(1);
+        }
+        ([command, ..], None) if ["status", "account", "end"].contains(&command.as_str()) => {
+            eprintln!("There is no evacuation in progress");
+            process::exit(1);
+        }
+        _ => usage_error(),
     }
-    println!("The final list of visitors:");
-    println!("{:#?}", visitor_list);
 }
 
-fn what_is_your_name() -> String {
-    let mut your_name = String::new();
-    // function chaining
-    stdin() // returns an object granting access to the standard input
-        .read_line(&mut your_name) // read_line is a method on the stdin object.
-        // &mut borrow the variable allowing changes to be made by the function
-        .expect("failed to readline"); // expect will unwrap a result object and terminate with the message if there is an error
+fn print_roll_call(roll_call: &RollCall) {
+    println!(
+        "{} on the roll, {} missing",
+        roll_call.roll.len(),
+        roll_call.missing().count()
+    );
+    for entry in &roll_call.roll {
+        let mark = match entry.accounted {
+            Some(time) => format!("[x] accounted for at {}", time),
+            None => "[ ] MISSING".to_string(),
+        };
+        println!(
+            "  {:<20} {}",
+            format!("{} (#{})", entry.name, entry.visitor),
+            mark
+        );
+    }
+}
+
+fn roll_call_or_exit() -> Option<RollCall> {
+    let audit_log = audit_log();
+    let entries = audit_log.entries().unwrap_or_else(|error| {
+        eprintln!("Could not read the audit log: {}", error);
+        process::exit(1);
+    });
+    RollCall::from_entries(&entries).unwrap_or_else(|error| {
+        eprintln!(
+            "Could not read the audit log: {}",
+            error.in_file(audit_log.path())
+        );
+        process::exit(1);
+    })
+}
 
-    // pre-fixing a variable with & creates a reference to the variable.
-    // A reference passes access to the variable itself, not a copy.
-    // this is called borrowing, the variable is lended to the function.
-    // lending with &mut permits the borrowing function to mutate the variable.
+fn run_verify() {
+    let audit_log = audit_log();
+    match audit_log.verify() {
+        Ok(Ok(verified)) => {
+            println!(
+                "{}: {} entries, chain intact",
+                audit_log.path().display(),
+                verified.entries
+            );
+            // Only a copy of this kept somewhere else can show that entries were cut off the end.
+            println!("last hash: {}", verified.head);
+        }
+        Ok(Err(broken)) => {
+            println!("{}: chain broken at {}", audit_log.path().display(), broken);
+            process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("Could not read the audit log: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+fn audit_log() -> AuditLog {
+    AuditLog::new(data_dir().join(AUDIT_FILE))
+}
+
+fn audit_or_exit(entry: &storage::Record) {
+    if let Err(error) = audit_log().append(entry) {
+        eprintln!("Could not write to the audit log: {}", error);
+        process::exit(1);
+    }
+}
+
+// Every arrival is written to the visit log as soon as it has been decided.
+fn log_visit(
+    visit_log: &VisitLog,
+    clock: &dyn Clock,
+    entered_name: &str,
+    visitor: Option<&Visitor>,
+    enrolled: bool,
+) {
+    let visit = VisitRecord {
+        time: clock.now(),
+        entered_name: entered_name.to_string(),
+        visitor: visitor.map(|visitor| visitor.id),
+        action: visitor.map(|visitor| visitor.action.clone()),
+        enrolled,
+    };
+    if let Err(error) = visit_log.append(&visit) {
+        eprintln!("Could not write to the audit log: {}", error);
+        process::exit(1);
+    }
+}
+
+// The ! return type means this function never returns, so it can be used where any type is expected.
+fn usage_error() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+// The hard coded list in default_visitors is only used the very first time, when there is no saved list yet.
+// The clock gives the date that ages in old files are counted back from, see storage.rs.
+fn load_or_exit(path: &Path, clock: &dyn Clock) -> VisitorRegistry {
+    let config = load_config_or_exit();
+    let mut registry = match storage::load_registry(path, config.local_date(clock.now())) {
+        Ok(Some(registry)) => registry,
+        Ok(None) => VisitorRegistry::from_visitors(default_visitors()),
+        Err(error) => {
+            eprintln!("Could not load the visitor list: {}", error);
+            process::exit(1);
+        }
+    };
+    // The aliases were read with the default matching, the one from the settings may make two of them clash.
+    if let Err(error) = registry.set_name_matching(config.matching) {
+        eprintln!(
+            "Could not load the visitor list: {} with the name matching in the settings",
+            error
+        );
+        process::exit(1);
+    }
+    registry
+}
+
+fn load_config_or_exit() -> Config {
+    match config::load_config(&data_dir().join(CONFIG_FILE)) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("Could not load the settings: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+// The built in message catalogs plus any in the messages directory, see messages.rs.
+fn load_messages_or_exit(config: &Config) -> Messages {
+    let language = &config.locale.default_language;
+    match Messages::load(&data_dir().join(MESSAGES_DIR), language) {
+        Ok(messages) if messages.has_language(language) => messages,
+        Ok(_) => {
+            eprintln!(
+                "There are no messages for the default language `{}`, add {}/{}.txt",
+                language, MESSAGES_DIR, language
+            );
+            process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("Could not load the messages: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+fn default_visitors() -> Vec<Visitor> {
+    vec![
+        Visitor::new(
+            "Bert",
+            "Hello Bert, enjoy your treehouse.",
+            VisitorAction::Accept,
+            Date::new(1981, 4, 2),
+        ),
+        Visitor::new(
+            "Steve",
+            "Hi Steve. Your milk is in the fridge.",
+            VisitorAction::AcceptWithNote {
+                note: String::from("Lactose-free milk is in the fridge"),
+            },
+            Date::new(2011, 6, 30),
+        ),
+        Visitor::new(
+            "Fred",
+            "Wow, who invited Fred?",
+            VisitorAction::Refuse,
+            Date::new(1996, 1, 20),
+        ),
+    ]
+}
+
+// Every command normally runs on the system clock. TREEHOUSE_NOW stops it at one moment instead,
+// which is handy for checking opening hours without waiting for them.
+fn door_clock() -> Box<dyn Clock> {
+    let Some(now) = env::var_os("TREEHOUSE_NOW") else {
+        return Box::new(SystemClock);
+    };
+    let now = now.to_string_lossy();
+    match Timestamp::parse(&now) {
+        Some(time) => Box::new(FixedClock(time)),
+        None => {
+            eprintln!(
+                "TREEHOUSE_NOW must be a time like 2026-10-18T16:00:00Z, not `{}`",
+                now
+            );
+            process::exit(2);
+        }
+    }
+}
+
+fn data_dir() -> PathBuf {
+    // env::var_os returns None when the variable is not set, unwrap_or_else supplies the default.
+    env::var_os("TREEHOUSE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR))
+}
+
+fn visitor_file_path() -> PathBuf {
+    data_dir().join(VISITOR_FILE)
+}
+
+// Saving happens after every change, so losing the program part way through loses nothing.
+fn save_or_exit(path: &Path, visitor_list: &VisitorRegistry) {
+    if let Err(error) = storage::save_registry(path, visitor_list) {
+        eprintln!("Could not save the visitor list: {}", error);
+        process::exit(1);
+    }
+}
+
+// Names come from stdin unless `--names <file>` is given, in which case the file is read one name per line.
+// Box<dyn NameSource> holds "some type that implements NameSource", chosen while the program runs.
+fn name_source(args: &[String]) -> Box<dyn NameSource> {
+    match args {
+        [] => Box::new(LineSource::stdin()),
+        [flag, path] if flag == "--names" => match LineSource::open(Path::new(path)) {
+            Ok(source) => Box::new(source),
+            Err(error) => {
+                eprintln!("Could not open {}: {}", path, error);
+                process::exit(1);
+            }
+        },
+        _ => usage_error(),
+    }
+}
+
+// Asks a follow up question when more than one visitor has the same name.
+// The answer is the visitor number that was handed out when they joined, see `rust-treehouse list`.
+// Names from a file can't answer, the next line is somebody else arriving, so nobody is picked.
+// The lifetime 'a says the returned visitor is borrowed from the same list as the candidates.
+fn which_one_are_you<'a>(
+    source: &mut dyn NameSource,
+    messages: &Messages,
+    name: &str,
+    candidates: &[&'a Visitor],
+) -> Option<&'a Visitor> {
+    if !source.is_interactive() {
+        return None;
+    }
+    let language = messages.default_language();
+    println!(
+        "{}",
+        messages.text(language, "which_one", &[("name", name)])
+    );
+    let answer = source.next_name().ok()?; // ok() turns the Result into an Option, so ? gives up on any error.
+    let id: VisitorId = answer.trim_start_matches('#').parse().ok()?;
+    // copied turns the iterator of &&Visitor into one of &Visitor.
+    candidates.iter().copied().find(|visitor| visitor.id == id)
+}
+
+// Offers the closest names on the list when a name isn't on it. Suggestions need someone to answer them,
+// so nothing is asked when names come from a file. Returns the visitor they picked, if any.
+fn did_you_mean<'a>(
+    source: &mut dyn NameSource,
+    messages: &Messages,
+    visitor_list: &'a VisitorRegistry,
+    name: &str,
+    settings: FuzzyMatching,
+) -> Option<&'a Visitor> {
+    let suggestions = fuzzy::suggestions(visitor_list, name, settings);
+    if suggestions.is_empty() || !source.is_interactive() {
+        return None;
+    }
+
+    let language = messages.default_language();
+    let picked = if let [only] = suggestions.as_slice() {
+        let values = [("name", name), ("suggestion", only.name.as_str())];
+        println!("{}", messages.text(language, "did_you_mean", &values));
+        let answer = source.next_name().ok()?;
+        messages.is_yes(language, &answer).then_some(only)
+    } else {
+        let values = [("name", name)];
+        println!("{}", messages.text(language, "did_you_mean_many", &values));
+        for (number, suggestion) in suggestions.iter().enumerate() {
+            println!("  {}) {}", number + 1, suggestion.name);
+        }
+        println!("{}", messages.text(language, "pick_number", &[]));
+        let answer: usize = source.next_name().ok()?.parse().ok()?;
+        // checked_sub returns None instead of underflowing when the answer is 0.
+        suggestions.get(answer.checked_sub(1)?)
+    }?;
+    visitor_list.get(picked.id)
+}
+
+// Asks a new visitor for their birth date so their age is known from the start.
+// Names from a file can't answer, and anyone may choose not to say, so None means "unknown".
+fn when_were_you_born(
+    source: &mut dyn NameSource,
+    messages: &Messages,
+    today: Date,
+) -> Option<Date> {
+    if !source.is_interactive() {
+        return None;
+    }
+    let language = messages.default_language();
+    loop {
+        println!("{}", messages.text(language, "ask_birth_date", &[]));
+        let answer = source.next_name().ok()?;
+        if answer.is_empty() {
+            return None;
+        }
+        match Date::parse(&answer) {
+            Some(date) if date <= today => return Some(date),
+            Some(_) => println!("{}", messages.text(language, "future_date", &[])),
+            None => println!(
+                "{}",
+                messages.text(language, "bad_date", &[("answer", &answer)])
+            ),
+        }
+    }
+}
+
+// Asks a new visitor which member vouches for them, until they name one who can or leave it empty.
+// The member's own standing decides whether they can, see sponsors::can_vouch.
+// Like the birth date, names from a file can't answer, so they never have a sponsor.
+fn who_vouches_for_you(
+    source: &mut dyn NameSource,
+    messages: &Messages,
+    visitor_list: &VisitorRegistry,
+    config: &Config,
+    now: Timestamp,
+) -> Option<VisitorId> {
+    if !source.is_interactive() {
+        return None;
+    }
+    let language = messages.default_language();
+    loop {
+        println!("{}", messages.text(language, "ask_sponsor", &[]));
+        let answer = what_is_your_name(source).ok()?;
+        if answer.is_empty() {
+            return None;
+        }
+        let sponsor = match visitor_list.lookup(&answer) {
+            Lookup::Found(visitor) => visitor,
+            Lookup::Ambiguous(visitors) => {
+                match which_one_are_you(source, messages, &answer, &visitors) {
+                    Some(visitor) => visitor,
+                    None => continue,
+                }
+            }
+            Lookup::NotFound => {
+                let values = [("sponsor", answer.as_str())];
+                println!("{}", messages.text(language, "sponsor_unknown", &values));
+                continue;
+            }
+        };
+        match sponsors::can_vouch(sponsor, &config.minors, now.to_local(config.utc_offset)) {
+            Ok(()) => return Some(sponsor.id),
+            Err(refusal) => {
+                let age = match refusal {
+                    VouchRefusal::Minor(age) => age.to_string(),
+                    _ => String::new(),
+                };
+                let values = [("sponsor", sponsor.name.as_str()), ("age", &age)];
+                println!(
+                    "{}",
+                    messages.text(language, refusal.message_key(), &values)
+                );
+            }
+        }
+    }
+}
 
-    your_name // lines that dont end in ; are returns.
-        .trim()
-        .to_lowercase()
+// &mut dyn NameSource borrows the source mutably, because reading a name moves it along to the next one.
+// pre-fixing a variable with & creates a reference to the variable.
+// A reference passes access to the variable itself, not a copy.
+// this is called borrowing, the variable is lended to the function.
+// lending with &mut permits the borrowing function to mutate the variable.
+fn what_is_your_name(source: &mut dyn NameSource) -> Result<String, InputError> {
+    // ? returns the error to the caller straight away, instead of terminating like expect used to.
+    // The name is kept as typed, apart from tidying spaces, so "Bert" is greeted as "Bert".
+    // Matching it against the list ignores case, see names.rs.
+    let your_name = source.next_name()?;
+    Ok(display_name(&your_name))
 }
@@ -0,0 +1,874 @@
+// Everything the door says, in more than one language.
+//
+// Each language has a catalog of messages looked up by key. English, Spanish, German and French are
+// built in. A file called messages/<language>.txt in the data directory can add another language or
+// change some of the built in messages, using the same [section] / key = value format as the other files:
+//
+//     [messages]
+//     language = es
+//     welcome = ¡Bienvenido a la casa del árbol, {name}!
+//     queue_ahead.one = Hay {count} persona delante de ti.
+//     queue_ahead.other = Hay {count} personas delante de ti.
+//
+// Words in braces are filled in when the message is used, e.g. {name}. Messages that depend on a number
+// have one line per plural form, named after the Unicode CLDR plural categories (zero, one, two, few,
+// many, other). Which category a number falls in depends on the language, see plural_category.
+// A `.zero` line is used for 0 in any language that has one, so "Nobody is ahead of you" reads
+// better than "There are 0 people ahead of you". `.other` is the fallback for every number.
+//
+// A message missing from a catalog is taken from English, so a partial translation still works.
+// Visitors can have a preferred language, everybody else hears the default from the config:
+//
+//     [locale]
+//     default = en
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::storage::{parse_records, ParseError, StorageError};
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+const EN: &[(&str, &str)] = &[
+    (
+        "ask_name",
+        "Hello, what's your name? (Leave empty and press ENTER to quit)",
+    ),
+    ("hello", "Hello {name}"),
+    ("welcome", "Welcome to the tree house, {name}"),
+    ("probation", "{name} is now a probationary member"),
+    ("refused", "Do not allow {name} in!"),
+    ("no_alcohol", "Do not serve alcohol to {name}"),
+    ("check_id", "Check {name}'s ID before serving alcohol"),
+    ("birthday_greeting", "Happy birthday, {name}!"),
+    ("access_allowed", "{name} may come in: {rule}"),
+    ("access_denied", "{name} may not come in now: {rule}"),
+    (
+        "access_outside",
+        "{name} may only come in at these times: {rules}",
+    ),
+    (
+        "access_unknown_group",
+        "{name} is in the group {group}, which the config doesn't have",
+    ),
+    (
+        "minor_outside_hours",
+        "{name} is under {age} and may only be here {hours}",
+    ),
+    (
+        "minor_no_guardian",
+        "{name} is under {age} and has no guardian on file",
+    ),
+    (
+        "minor_guardian_not_inside",
+        "{name} is under {age} and none of their guardians ({guardians}) is inside",
+    ),
+    (
+        "minor_age_unknown",
+        "Nobody knows how old {name} is, so the rules for under {age}s can't be checked",
+    ),
+    ("flagged", "Warning only: {issue}"),
+    ("lapsed", "{name}'s guest pass can't be used now"),
+    (
+        "pass_valid",
+        "{name} is a guest of {sponsor}, the pass is valid until {until}",
+    ),
+    (
+        "pass_valid_limited.one",
+        "{name} is a guest of {sponsor} on a single-visit pass, valid until {until}",
+    ),
+    (
+        "pass_valid_limited.other",
+        "{name} is a guest of {sponsor}, visit {uses} of {count}, valid until {until}",
+    ),
+    (
+        "pass_not_yet_valid",
+        "{name}'s guest pass is only valid from {from}",
+    ),
+    ("pass_expired", "{name}'s guest pass expired at {until}"),
+    (
+        "pass_used_up.one",
+        "{name}'s guest pass was for {count} visit and it has been used",
+    ),
+    (
+        "pass_used_up.other",
+        "{name}'s guest pass was for {count} visits and they have all been used",
+    ),
+    (
+        "ask_sponsor",
+        "Is a member vouching for you? Type their name, or leave empty if nobody is",
+    ),
+    ("sponsor_unknown", "{sponsor} is not on the visitor list"),
+    (
+        "sponsor_refused",
+        "{sponsor} is not allowed in, so they can't vouch for anybody",
+    ),
+    (
+        "sponsor_on_probation",
+        "{sponsor} is still on probation, so they can't vouch for anybody yet",
+    ),
+    (
+        "sponsor_is_guest",
+        "{sponsor} is only here on a guest pass, so they can't vouch for anybody",
+    ),
+    (
+        "sponsor_is_minor",
+        "{sponsor} is under {age}, so they can't vouch for anybody",
+    ),
+    (
+        "sponsor_required",
+        "Sorry {name}, new visitors need a member to vouch for them",
+    ),
+    ("sponsored_by", "{name} is here on {sponsor}'s word"),
+    (
+        "sponsor_notice",
+        "{name}, you vouched for {guest} at {time}",
+    ),
+    (
+        "probation_promoted",
+        "{name} has finished their probation and is now a member",
+    ),
+    (
+        "probation_expired",
+        "{name}'s probation has run out, they are no longer let in",
+    ),
+    ("not_on_list", "{name} is not on the visitor list."),
+    (
+        "which_one",
+        "There is more than one {name} on the list. What is your visitor number?",
+    ),
+    (
+        "which_one_failed",
+        "Sorry {name}, I couldn't tell which one you are.",
+    ),
+    (
+        "did_you_mean",
+        "{name} isn't on the list. Did you mean {suggestion}? (yes/no)",
+    ),
+    (
+        "did_you_mean_many",
+        "{name} isn't on the list. Did you mean one of these?",
+    ),
+    (
+        "pick_number",
+        "Enter a number, or leave empty if none of these is you.",
+    ),
+    ("yes", "yes, y"),
+    (
+        "ask_birth_date",
+        "When were you born? (YYYY-MM-DD, leave empty if you'd rather not say)",
+    ),
+    ("future_date", "That date hasn't happened yet."),
+    ("bad_date", "Sorry, {answer} isn't a date I understand."),
+    ("goodbye", "Goodbye {name}, you were here for {duration}"),
+    (
+        "queue_full",
+        "The tree house is full, {name}. You are in the queue.",
+    ),
+    (
+        "queue_still_full",
+        "Sorry {name}, the tree house is still full. You are still in the queue.",
+    ),
+    ("queue_ahead.zero", "Nobody is ahead of you."),
+    ("queue_ahead.one", "There is {count} person ahead of you."),
+    (
+        "queue_ahead.other",
+        "There are {count} people ahead of you.",
+    ),
+    (
+        "come_in_now",
+        "{name} can come in now, after waiting {duration}",
+    ),
+    (
+        "turned_away_from_queue",
+        "{name} was waiting, but can't come in any more and has left the queue",
+    ),
+    (
+        "evacuating",
+        "The tree house is being evacuated. Nobody can come in.",
+    ),
+    ("never", "never"),
+    ("final_list", "The final list of visitors:"),
+];
+
+const ES: &[(&str, &str)] = &[
+    (
+        "ask_name",
+        "Hola, ¿cómo te llamas? (Déjalo vacío y pulsa ENTER para salir)",
+    ),
+    ("hello", "Hola {name}"),
+    ("welcome", "Bienvenido a la casa del árbol, {name}"),
+    ("probation", "{name} es ahora miembro a prueba"),
+    ("refused", "¡No dejes entrar a {name}!"),
+    ("no_alcohol", "No sirvas alcohol a {name}"),
+    (
+        "check_id",
+        "Comprueba el documento de {name} antes de servir alcohol",
+    ),
+    ("birthday_greeting", "¡Feliz cumpleaños, {name}!"),
+    ("access_allowed", "{name} puede entrar: {rule}"),
+    ("access_denied", "{name} no puede entrar ahora: {rule}"),
+    (
+        "access_outside",
+        "{name} solo puede entrar en estos horarios: {rules}",
+    ),
+    (
+        "access_unknown_group",
+        "{name} está en el grupo {group}, que no existe en la configuración",
+    ),
+    ("minor_outside_hours", "{name} tiene menos de {age} años y solo puede estar aquí {hours}"),
+    ("minor_no_guardian", "{name} tiene menos de {age} años y no tiene ningún tutor registrado"),
+    ("minor_guardian_not_inside", "{name} tiene menos de {age} años y ninguno de sus tutores ({guardians}) está dentro"),
+    ("minor_age_unknown", "No se sabe la edad de {name}, así que no se pueden comprobar las normas para menores de {age}"),
+    ("flagged", "Solo un aviso: {issue}"),
+    ("lapsed", "El pase de invitado de {name} no se puede usar ahora"),
+    ("pass_valid", "{name} es invitado de {sponsor}, el pase es válido hasta {until}"),
+    ("pass_valid_limited.one", "{name} es invitado de {sponsor} con un pase de una sola visita, válido hasta {until}"),
+    ("pass_valid_limited.other", "{name} es invitado de {sponsor}, visita {uses} de {count}, válido hasta {until}"),
+    ("pass_not_yet_valid", "El pase de invitado de {name} solo es válido desde {from}"),
+    ("pass_expired", "El pase de invitado de {name} caducó el {until}"),
+    ("pass_used_up.one", "El pase de invitado de {name} era para {count} visita y ya se ha usado"),
+    ("pass_used_up.other", "El pase de invitado de {name} era para {count} visitas y ya se han usado todas"),
+    ("ask_sponsor", "¿Algún miembro responde por ti? Escribe su nombre, o déjalo vacío si nadie lo hace"),
+    ("sponsor_unknown", "{sponsor} no está en la lista de visitantes"),
+    ("sponsor_refused", "{sponsor} no tiene permitida la entrada, así que no puede responder por nadie"),
+    ("sponsor_on_probation", "{sponsor} todavía está a prueba, así que aún no puede responder por nadie"),
+    ("sponsor_is_guest", "{sponsor} solo está aquí con un pase de invitado, así que no puede responder por nadie"),
+    ("sponsor_is_minor", "{sponsor} tiene menos de {age} años, así que no puede responder por nadie"),
+    ("sponsor_required", "Lo siento {name}, los visitantes nuevos necesitan que un miembro responda por ellos"),
+    ("sponsored_by", "{name} está aquí por recomendación de {sponsor}"),
+    ("sponsor_notice", "{name}, respondiste por {guest} el {time}"),
+    ("probation_promoted", "{name} ha terminado su periodo de prueba y ya es miembro"),
+    ("probation_expired", "El periodo de prueba de {name} ha terminado sin éxito, ya no se le deja entrar"),
+    ("not_on_list", "{name} no está en la lista de visitantes."),
+    (
+        "which_one",
+        "Hay más de un {name} en la lista. ¿Cuál es tu número de visitante?",
+    ),
+    (
+        "which_one_failed",
+        "Lo siento {name}, no sé cuál de ellos eres.",
+    ),
+    (
+        "did_you_mean",
+        "{name} no está en la lista. ¿Querías decir {suggestion}? (sí/no)",
+    ),
+    (
+        "did_you_mean_many",
+        "{name} no está en la lista. ¿Querías decir uno de estos?",
+    ),
+    (
+        "pick_number",
+        "Escribe un número, o déjalo vacío si no eres ninguno de ellos.",
+    ),
+    ("yes", "sí, si, s"),
+    (
+        "ask_birth_date",
+        "¿Cuándo naciste? (AAAA-MM-DD, déjalo vacío si prefieres no decirlo)",
+    ),
+    ("future_date", "Esa fecha todavía no ha llegado."),
+    ("bad_date", "Lo siento, no entiendo la fecha {answer}."),
+    ("goodbye", "Adiós {name}, has estado aquí {duration}"),
+    (
+        "queue_full",
+        "La casa del árbol está llena, {name}. Estás en la cola.",
+    ),
+    (
+        "queue_still_full",
+        "Lo siento {name}, la casa del árbol sigue llena. Sigues en la cola.",
+    ),
+    ("queue_ahead.zero", "No hay nadie delante de ti."),
+    ("queue_ahead.one", "Hay {count} persona delante de ti."),
+    ("queue_ahead.other", "Hay {count} personas delante de ti."),
+    (
+        "come_in_now",
+        "{name} ya puede entrar, tras esperar {duration}",
+    ),
+    (
+        "turned_away_from_queue",
+        "{name} estaba esperando, pero ya no puede entrar y ha salido de la cola",
+    ),
+    (
+        "evacuating",
+        "Se está evacuando la casa del árbol. Nadie puede entrar.",
+    ),
+    ("never", "nunca"),
+    ("final_list", "La lista final de visitantes:"),
+];
+
+const DE: &[(&str, &str)] = &[
+    (
+        "ask_name",
+        "Hallo, wie heißt du? (Leer lassen und ENTER drücken zum Beenden)",
+    ),
+    ("hello", "Hallo {name}"),
+    ("welcome", "Willkommen im Baumhaus, {name}"),
+    ("probation", "{name} ist jetzt Mitglied auf Probe"),
+    ("refused", "Lass {name} nicht herein!"),
+    ("no_alcohol", "Schenk {name} keinen Alkohol aus"),
+    (
+        "check_id",
+        "Prüfe den Ausweis von {name}, bevor du Alkohol ausschenkst",
+    ),
+    ("birthday_greeting", "Alles Gute zum Geburtstag, {name}!"),
+    ("access_allowed", "{name} darf herein: {rule}"),
+    ("access_denied", "{name} darf jetzt nicht herein: {rule}"),
+    ("access_outside", "{name} darf nur zu diesen Zeiten herein: {rules}"),
+    ("access_unknown_group", "{name} ist in der Gruppe {group}, die es in der Konfiguration nicht gibt"),
+    ("minor_outside_hours", "{name} ist unter {age} und darf nur {hours} hier sein"),
+    ("minor_no_guardian", "{name} ist unter {age} und hat keinen eingetragenen Erziehungsberechtigten"),
+    ("minor_guardian_not_inside", "{name} ist unter {age} und keiner der Erziehungsberechtigten ({guardians}) ist drinnen"),
+    ("minor_age_unknown", "Niemand weiß, wie alt {name} ist, daher können die Regeln für unter {age}-Jährige nicht geprüft werden"),
+    ("flagged", "Nur ein Hinweis: {issue}"),
+    ("lapsed", "Der Gästepass von {name} gilt gerade nicht"),
+    ("pass_valid", "{name} ist Gast von {sponsor}, der Pass gilt bis {until}"),
+    ("pass_valid_limited.one", "{name} ist Gast von {sponsor} mit einem Pass für einen Besuch, gültig bis {until}"),
+    ("pass_valid_limited.other", "{name} ist Gast von {sponsor}, Besuch {uses} von {count}, gültig bis {until}"),
+    ("pass_not_yet_valid", "Der Gästepass von {name} gilt erst ab {from}"),
+    ("pass_expired", "Der Gästepass von {name} ist am {until} abgelaufen"),
+    ("pass_used_up.one", "Der Gästepass von {name} war für {count} Besuch und ist aufgebraucht"),
+    ("pass_used_up.other", "Der Gästepass von {name} war für {count} Besuche und ist aufgebraucht"),
+    ("ask_sponsor", "Bürgt ein Mitglied für dich? Gib den Namen ein, oder lass es leer, wenn niemand bürgt"),
+    ("sponsor_unknown", "{sponsor} steht nicht auf der Besucherliste"),
+    ("sponsor_refused", "{sponsor} darf nicht hinein und kann daher für niemanden bürgen"),
+    ("sponsor_on_probation", "{sponsor} ist noch auf Probe und kann daher noch für niemanden bürgen"),
+    ("sponsor_is_guest", "{sponsor} ist nur mit einem Gästepass hier und kann daher für niemanden bürgen"),
+    ("sponsor_is_minor", "{sponsor} ist unter {age} und kann daher für niemanden bürgen"),
+    ("sponsor_required", "Tut mir leid, {name}, neue Besucher brauchen ein Mitglied, das für sie bürgt"),
+    ("sponsored_by", "{name} ist auf das Wort von {sponsor} hier"),
+    ("sponsor_notice", "{name}, du hast am {time} für {guest} gebürgt"),
+    ("probation_promoted", "{name} hat die Probezeit bestanden und ist jetzt Mitglied"),
+    ("probation_expired", "Die Probezeit von {name} ist abgelaufen, {name} wird nicht mehr hineingelassen"),
+    ("not_on_list", "{name} steht nicht auf der Besucherliste."),
+    (
+        "which_one",
+        "Es gibt mehr als eine Person namens {name}. Wie lautet deine Besuchernummer?",
+    ),
+    (
+        "which_one_failed",
+        "Tut mir leid {name}, ich weiß nicht, wer von ihnen du bist.",
+    ),
+    (
+        "did_you_mean",
+        "{name} steht nicht auf der Liste. Meintest du {suggestion}? (ja/nein)",
+    ),
+    (
+        "did_you_mean_many",
+        "{name} steht nicht auf der Liste. Meintest du einen von diesen?",
+    ),
+    (
+        "pick_number",
+        "Gib eine Nummer ein, oder lass es leer, wenn keiner davon du bist.",
+    ),
+    ("yes", "ja, j"),
+    (
+        "ask_birth_date",
+        "Wann bist du geboren? (JJJJ-MM-TT, leer lassen, wenn du es nicht sagen möchtest)",
+    ),
+    ("future_date", "Dieses Datum liegt in der Zukunft."),
+    ("bad_date", "Tut mir leid, {answer} verstehe ich nicht als Datum."),
+    ("goodbye", "Tschüss {name}, du warst {duration} hier"),
+    (
+        "queue_full",
+        "Das Baumhaus ist voll, {name}. Du stehst in der Warteschlange.",
+    ),
+    (
+        "queue_still_full",
+        "Tut mir leid {name}, das Baumhaus ist immer noch voll. Du stehst weiter in der Warteschlange.",
+    ),
+    ("queue_ahead.zero", "Niemand ist vor dir."),
+    ("queue_ahead.one", "{count} Person ist vor dir."),
+    ("queue_ahead.other", "{count} Personen sind vor dir."),
+    (
+        "come_in_now",
+        "{name} darf jetzt herein, nach {duration} Wartezeit",
+    ),
+    (
+        "turned_away_from_queue",
+        "{name} hat gewartet, darf aber nicht mehr herein und ist aus der Warteschlange genommen",
+    ),
+    (
+        "evacuating",
+        "Das Baumhaus wird evakuiert. Niemand darf herein.",
+    ),
+    ("never", "nie"),
+    ("final_list", "Die endgültige Besucherliste:"),
+];
+
+const FR: &[(&str, &str)] = &[
+    (
+        "ask_name",
+        "Bonjour, comment t'appelles-tu ? (Laisse vide et appuie sur ENTRÉE pour quitter)",
+    ),
+    ("hello", "Bonjour {name}"),
+    ("welcome", "Bienvenue dans la cabane, {name}"),
+    ("probation", "{name} est maintenant membre à l'essai"),
+    ("refused", "Ne laisse pas entrer {name} !"),
+    ("no_alcohol", "Ne sers pas d'alcool à {name}"),
+    (
+        "check_id",
+        "Vérifie la pièce d'identité de {name} avant de servir de l'alcool",
+    ),
+    ("birthday_greeting", "Joyeux anniversaire, {name} !"),
+    ("access_allowed", "{name} peut entrer : {rule}"),
+    (
+        "access_denied",
+        "{name} ne peut pas entrer maintenant : {rule}",
+    ),
+    (
+        "access_outside",
+        "{name} ne peut entrer qu'à ces horaires : {rules}",
+    ),
+    (
+        "access_unknown_group",
+        "{name} est dans le groupe {group}, qui n'existe pas dans la configuration",
+    ),
+    ("minor_outside_hours", "{name} a moins de {age} ans et ne peut être ici que {hours}"),
+    ("minor_no_guardian", "{name} a moins de {age} ans et n'a aucun responsable enregistré"),
+    ("minor_guardian_not_inside", "{name} a moins de {age} ans et aucun de ses responsables ({guardians}) n'est à l'intérieur"),
+    ("minor_age_unknown", "Personne ne connaît l'âge de {name}, donc les règles pour les moins de {age} ans ne peuvent pas être vérifiées"),
+    ("flagged", "Simple avertissement : {issue}"),
+    ("lapsed", "Le pass invité de {name} n'est pas utilisable maintenant"),
+    ("pass_valid", "{name} est invité par {sponsor}, le pass est valable jusqu'au {until}"),
+    ("pass_valid_limited.one", "{name} est invité par {sponsor} avec un pass pour une seule visite, valable jusqu'au {until}"),
+    ("pass_valid_limited.other", "{name} est invité par {sponsor}, visite {uses} sur {count}, valable jusqu'au {until}"),
+    ("pass_not_yet_valid", "Le pass invité de {name} n'est valable qu'à partir du {from}"),
+    ("pass_expired", "Le pass invité de {name} a expiré le {until}"),
+    ("pass_used_up.one", "Le pass invité de {name} était pour {count} visite et a été utilisé"),
+    ("pass_used_up.other", "Le pass invité de {name} était pour {count} visites et elles ont toutes été utilisées"),
+    ("ask_sponsor", "Un membre se porte-t-il garant pour toi ? Tape son nom, ou laisse vide si personne ne le fait"),
+    ("sponsor_unknown", "{sponsor} n'est pas sur la liste des visiteurs"),
+    ("sponsor_refused", "{sponsor} n'a pas le droit d'entrer et ne peut donc se porter garant pour personne"),
+    ("sponsor_on_probation", "{sponsor} est encore à l'essai et ne peut donc pas encore se porter garant"),
+    ("sponsor_is_guest", "{sponsor} n'est ici qu'avec un pass invité et ne peut donc se porter garant pour personne"),
+    ("sponsor_is_minor", "{sponsor} a moins de {age} ans et ne peut donc se porter garant pour personne"),
+    ("sponsor_required", "Désolé {name}, les nouveaux visiteurs ont besoin qu'un membre se porte garant pour eux"),
+    ("sponsored_by", "{name} est ici sur la parole de {sponsor}"),
+    ("sponsor_notice", "{name}, tu t'es porté garant pour {guest} le {time}"),
+    ("probation_promoted", "{name} a terminé sa période d'essai et est maintenant membre"),
+    ("probation_expired", "La période d'essai de {name} a expiré, {name} n'est plus autorisé à entrer"),
+    (
+        "not_on_list",
+        "{name} n'est pas sur la liste des visiteurs.",
+    ),
+    (
+        "which_one",
+        "Il y a plusieurs {name} sur la liste. Quel est ton numéro de visiteur ?",
+    ),
+    (
+        "which_one_failed",
+        "Désolé {name}, je ne sais pas lequel tu es.",
+    ),
+    (
+        "did_you_mean",
+        "{name} n'est pas sur la liste. Voulais-tu dire {suggestion} ? (oui/non)",
+    ),
+    (
+        "did_you_mean_many",
+        "{name} n'est pas sur la liste. Voulais-tu dire l'un d'eux ?",
+    ),
+    (
+        "pick_number",
+        "Entre un numéro, ou laisse vide si aucun n'est toi.",
+    ),
+    ("yes", "oui, o"),
+    (
+        "ask_birth_date",
+        "Quand es-tu né ? (AAAA-MM-JJ, laisse vide si tu préfères ne pas le dire)",
+    ),
+    ("future_date", "Cette date n'est pas encore arrivée."),
+    ("bad_date", "Désolé, je ne comprends pas la date {answer}."),
+    ("goodbye", "Au revoir {name}, tu es resté {duration}"),
+    (
+        "queue_full",
+        "La cabane est pleine, {name}. Tu es dans la file d'attente.",
+    ),
+    (
+        "queue_still_full",
+        "Désolé {name}, la cabane est toujours pleine. Tu es toujours dans la file d'attente.",
+    ),
+    ("queue_ahead.zero", "Personne n'est devant toi."),
+    ("queue_ahead.one", "{count} personne est devant toi."),
+    ("queue_ahead.other", "{count} personnes sont devant toi."),
+    (
+        "come_in_now",
+        "{name} peut entrer maintenant, après {duration} d'attente",
+    ),
+    (
+        "turned_away_from_queue",
+        "{name} attendait, mais ne peut plus entrer et a quitté la file d'attente",
+    ),
+    (
+        "evacuating",
+        "La cabane est en cours d'évacuation. Personne ne peut entrer.",
+    ),
+    ("never", "jamais"),
+    ("final_list", "La liste finale des visiteurs :"),
+];
+
+const BUILT_IN: [(&str, &[(&str, &str)]); 4] = [("en", EN), ("es", ES), ("de", DE), ("fr", FR)];
+
+// The [locale] section of the config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocaleSettings {
+    pub default_language: String,
+}
+
+impl Default for LocaleSettings {
+    fn default() -> Self {
+        Self {
+            default_language: DEFAULT_LANGUAGE.to_string(),
+        }
+    }
+}
+
+// The CLDR plural categories. Most languages only use a few of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluralCategory {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralCategory {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PluralCategory::Zero => "zero",
+            PluralCategory::One => "one",
+            PluralCategory::Two => "two",
+            PluralCategory::Few => "few",
+            PluralCategory::Many => "many",
+            PluralCategory::Other => "other",
+        }
+    }
+}
+
+// The CLDR cardinal rules for whole numbers, for the languages the treehouse has needed so far.
+// Anything not listed follows English, which only tells 1 apart from every other number.
+pub fn plural_category(language: &str, n: u64) -> PluralCategory {
+    match base_language(language) {
+        // No plural forms at all.
+        "ja" | "zh" | "ko" | "vi" | "th" | "id" => PluralCategory::Other,
+        // 0 and 1 are both singular.
+        "fr" | "pt" | "hi" => {
+            if n <= 1 {
+                PluralCategory::One
+            } else {
+                PluralCategory::Other
+            }
+        }
+        "ru" | "uk" | "be" => match (n % 10, n % 100) {
+            (1, rest) if rest != 11 => PluralCategory::One,
+            (2..=4, rest) if !(12..=14).contains(&rest) => PluralCategory::Few,
+            _ => PluralCategory::Many,
+        },
+        "pl" => match (n, n % 10, n % 100) {
+            (1, _, _) => PluralCategory::One,
+            (_, 2..=4, rest) if !(12..=14).contains(&rest) => PluralCategory::Few,
+            _ => PluralCategory::Many,
+        },
+        "cs" | "sk" => match n {
+            1 => PluralCategory::One,
+            2..=4 => PluralCategory::Few,
+            _ => PluralCategory::Other,
+        },
+        "ar" => match (n, n % 100) {
+            (0, _) => PluralCategory::Zero,
+            (1, _) => PluralCategory::One,
+            (2, _) => PluralCategory::Two,
+            (_, 3..=10) => PluralCategory::Few,
+            (_, 11..=99) => PluralCategory::Many,
+            _ => PluralCategory::Other,
+        },
+        _ => {
+            if n == 1 {
+                PluralCategory::One
+            } else {
+                PluralCategory::Other
+            }
+        }
+    }
+}
+
+// "es-MX" and "es_MX" both become "es". Catalogs are looked up by the base language only.
+pub fn base_language(language: &str) -> &str {
+    language.split(['-', '_']).next().unwrap_or(language)
+}
+
+// Language codes are compared in lower case, so "ES" and "es" are the same language.
+pub fn normalize_language(language: &str) -> String {
+    base_language(language.trim()).to_ascii_lowercase()
+}
+
+pub fn is_valid_language(language: &str) -> bool {
+    let base = normalize_language(language);
+    (2..=3).contains(&base.len()) && base.chars().all(|c| c.is_ascii_lowercase())
+}
+
+// Fills in the {words} in a message. Words without a value are left as they are.
+// It goes through the message once from left to right, so a value that itself contains
+// something like {name}, e.g. a visitor called "{name}", is never filled in again.
+fn fill(text: &str, values: &[(&str, &str)]) -> String {
+    let mut filled = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            values
+                .iter()
+                .find(|(word, _)| *word == &after[..end])
+                .map(|(_, value)| (*value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                filled.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = after;
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+// All catalogs, and which language to use when nobody said.
+#[derive(Debug, Clone)]
+pub struct Messages {
+    catalogs: HashMap<String, HashMap<String, String>>,
+    default_language: String,
+}
+
+impl Default for Messages {
+    fn default() -> Self {
+        let catalogs = BUILT_IN
+            .iter()
+            .map(|(language, messages)| {
+                let messages = messages
+                    .iter()
+                    .map(|(key, text)| (key.to_string(), text.to_string()))
+                    .collect();
+                (language.to_string(), messages)
+            })
+            .collect();
+        Self {
+            catalogs,
+            default_language: DEFAULT_LANGUAGE.to_string(),
+        }
+    }
+}
+
+impl Messages {
+    // The built in catalogs, plus every messages/<language>.txt file in dir.
+    // A directory that doesn't exist just means there are no extra catalogs.
+    pub fn load(dir: &Path, default_language: &str) -> Result<Self, StorageError> {
+        let mut messages = Self {
+            default_language: normalize_language(default_language),
+            ..Self::default()
+        };
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(messages),
+            Err(source) => {
+                return Err(StorageError::Io {
+                    path: dir.to_path_buf(),
+                    source,
+                })
+            }
+        };
+        for entry in entries {
+            let path = entry
+                .map_err(|source| StorageError::Io {
+                    path: dir.to_path_buf(),
+                    source,
+                })?
+                .path();
+            if path.extension().is_some_and(|extension| extension == "txt") {
+                let text = fs::read_to_string(&path).map_err(|source| StorageError::Io {
+                    path: path.clone(),
+                    source,
+                })?;
+                messages
+                    .add_catalog(&text)
+                    .map_err(|error| error.in_file(&path))?;
+            }
+        }
+        Ok(messages)
+    }
+
+    // Adds the messages in a catalog file to its language, replacing any with the same key.
+    pub fn add_catalog(&mut self, text: &str) -> Result<(), ParseError> {
+        for record in parse_records(text)? {
+            if record.kind != "messages" {
+                return Err(ParseError::new(
+                    record.line,
+                    format!("unknown section [{}]", record.kind),
+                ));
+            }
+            let language = record.require("language")?;
+            if !is_valid_language(language) {
+                return Err(ParseError::new(
+                    record.line,
+                    format!("`{}` is not a language code like en or es", language),
+                ));
+            }
+            let catalog = self
+                .catalogs
+                .entry(normalize_language(language))
+                .or_default();
+            for (key, text) in record.fields.iter().filter(|(key, _)| key != "language") {
+                catalog.insert(key.clone(), text.clone());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn default_language(&self) -> &str {
+        &self.default_language
+    }
+
+    pub fn has_language(&self, language: &str) -> bool {
+        self.catalogs.contains_key(&normalize_language(language))
+    }
+
+    // The language to talk to someone in: theirs if we have a catalog for it, otherwise the default.
+    pub fn language_for<'a>(&'a self, preferred: Option<&'a str>) -> &'a str {
+        match preferred {
+            Some(language) if self.has_language(language) => base_language(language),
+            _ => &self.default_language,
+        }
+    }
+
+    fn lookup(&self, language: &str, key: &str) -> Option<&str> {
+        self.catalogs
+            .get(&normalize_language(language))
+            .and_then(|catalog| catalog.get(key))
+            .map(String::as_str)
+    }
+
+    // The message in the language, falling back to the default language and then to English.
+    // A key that is missing everywhere is returned as it is, so a typo shows up instead of nothing.
+    fn raw<'a>(&'a self, language: &str, key: &'a str) -> &'a str {
+        self.lookup(language, key)
+            .or_else(|| self.lookup(&self.default_language, key))
+            .or_else(|| self.lookup(DEFAULT_LANGUAGE, key))
+            .unwrap_or(key)
+    }
+
+    pub fn text(&self, language: &str, key: &str, values: &[(&str, &str)]) -> String {
+        fill(self.raw(language, key), values)
+    }
+
+    // A message that depends on count, see the note at the top of the file. {count} is filled in too.
+    pub fn plural(&self, language: &str, key: &str, count: u64, values: &[(&str, &str)]) -> String {
+        let category = plural_category(language, count);
+        let mut forms = Vec::new();
+        if count == 0 {
+            forms.push(format!("{}.zero", key));
+        }
+        forms.push(format!("{}.{}", key, category.label()));
+        forms.push(format!("{}.other", key));
+
+        // The visitor's own language gets every chance before falling back to another one.
+        let text = forms
+            .iter()
+            .find_map(|form| self.lookup(language, form))
+            .map(str::to_string)
+            .unwrap_or_else(|| self.raw(language, &forms[forms.len() - 1]).to_string());
+
+        let count = count.to_string();
+        let mut all_values = vec![("count", count.as_str())];
+        all_values.extend_from_slice(values);
+        fill(&text, &all_values)
+    }
+
+    // Whether an answer means yes in the language, e.g. "yes" or "y" in English.
+    pub fn is_yes(&self, language: &str, answer: &str) -> bool {
+        let answer = answer.trim().to_lowercase();
+        self.raw(language, "yes")
+            .split(',')
+            .any(|word| word.trim().to_lowercase() == answer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PluralCategory::*;
+
+    fn categories(language: &str, numbers: &[u64]) -> Vec<PluralCategory> {
+        numbers
+            .iter()
+            .map(|n| plural_category(language, *n))
+            .collect()
+    }
+
+    #[test]
+    fn cldr_plural_categories() {
+        assert_eq!(categories("en", &[0, 1, 2, 11]), [Other, One, Other, Other]);
+        assert_eq!(categories("fr", &[0, 1, 2]), [One, One, Other]);
+        assert_eq!(categories("ja", &[1]), [Other]);
+        assert_eq!(
+            categories("ru", &[1, 2, 5, 11, 12, 21, 22, 25, 111]),
+            [One, Few, Many, Many, Many, One, Few, Many, Many]
+        );
+        assert_eq!(
+            categories("pl", &[1, 2, 5, 12, 21, 22]),
+            [One, Few, Many, Many, Many, Few]
+        );
+        assert_eq!(categories("cs", &[1, 3, 5]), [One, Few, Other]);
+        assert_eq!(
+            categories("ar", &[0, 1, 2, 3, 11, 100, 103]),
+            [Zero, One, Two, Few, Many, Other, Few]
+        );
+        assert_eq!(categories("pt-BR", &[0, 2]), [One, Other]);
+    }
+
+    #[test]
+    fn plural_messages_fall_back_to_other() {
+        let mut messages = Messages::default();
+        messages
+            .add_catalog(
+                "[messages]\nlanguage = ru\nitems.one = {count} one\nitems.few = {count} few\nitems.other = {count} other\n",
+            )
+            .unwrap();
+        assert_eq!(messages.plural("ru", "items", 1, &[]), "1 one");
+        assert_eq!(messages.plural("ru", "items", 3, &[]), "3 few");
+        assert_eq!(messages.plural("ru", "items", 5, &[]), "5 other");
+        assert_eq!(
+            messages.plural("en", "queue_ahead", 1, &[]),
+            messages.text("en", "queue_ahead.one", &[("count", "1")])
+        );
+    }
+
+    #[test]
+    fn missing_messages_come_from_english() {
+        let messages = Messages::default();
+        assert_eq!(
+            messages.text("xx", "hello", &[("name", "Bert")]),
+            "Hello Bert"
+        );
+        assert_eq!(messages.text("es", "no_such_key", &[]), "no_such_key");
+        assert!(messages.is_yes("es", " Sí "));
+        assert!(!messages.is_yes("en", "sí"));
+    }
+
+    #[test]
+    fn values_are_filled_in_once() {
+        let values = [("name", "{guest}"), ("guest", "Bert")];
+        assert_eq!(fill("{name} and {guest}", &values), "{guest} and Bert");
+        assert_eq!(
+            fill("{{name}} {unknown} {name", &values),
+            "{{guest}} {unknown} {name"
+        );
+        assert_eq!(fill("Olá {name}!", &[("name", "José")]), "Olá José!");
+    }
+}
@@ -0,0 +1,293 @@
+// Extra rules for visitors under age: a curfew, and an adult who has to be inside first.
+//
+// They are set in the [minors] section of the config file and are all off until something is set:
+//
+//     [minors]
+//     age = 18                       # anybody younger is a minor, defaults to 18
+//     allowed_hours = daily 09:00-21:00
+//     allowed_hours = fri,sat 09:00-22:00
+//     outside_hours = deny           # or flag, to let them in with a warning
+//     require_guardian = true        # one of their guardians has to be checked in
+//     unknown_age = flag             # or deny, or minor, see below
+//
+// allowed_hours is a schedule like the ones in access.rs and may be repeated. A minor fits the
+// curfew if any of them covers the time. Guardians are linked to a visitor with `add-guardian`
+// and stored with them, see storage.rs.
+//
+// Somebody whose age isn't known is neither a minor nor an adult. With unknown_age = flag they are let
+// in with a warning that the rules couldn't be checked, deny keeps them out, and minor applies the
+// rules as if they were under age.
+
+use crate::access::Schedule;
+use crate::clock::Timestamp;
+use crate::occupancy::Occupancy;
+use crate::{Visitor, VisitorId};
+
+// What happens when a rule isn't met.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Enforcement {
+    #[default]
+    Deny,
+    Flag, // let them in, but tell whoever is at the door.
+}
+
+impl Enforcement {
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "deny" => Some(Enforcement::Deny),
+            "flag" => Some(Enforcement::Flag),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownAge {
+    #[default]
+    Flag,
+    Deny,
+    AsMinor,
+}
+
+impl UnknownAge {
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "flag" => Some(UnknownAge::Flag),
+            "deny" => Some(UnknownAge::Deny),
+            "minor" => Some(UnknownAge::AsMinor),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinorPolicy {
+    pub age: u8,
+    pub allowed_hours: Vec<Schedule>, // empty means no curfew.
+    pub outside_hours: Enforcement,
+    pub require_guardian: bool,
+    pub unknown_age: UnknownAge,
+}
+
+impl Default for MinorPolicy {
+    fn default() -> Self {
+        Self {
+            age: 18,
+            allowed_hours: Vec::new(),
+            outside_hours: Enforcement::Deny,
+            require_guardian: false,
+            unknown_age: UnknownAge::Flag,
+        }
+    }
+}
+
+// Which rule a minor didn't meet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MinorIssue {
+    OutsideHours(Vec<Schedule>),
+    NoGuardian, // nobody is linked to them.
+    GuardianNotInside(Vec<VisitorId>),
+    AgeUnknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinorFinding {
+    pub issue: MinorIssue,
+    pub enforcement: Enforcement,
+    pub under: u8, // the policy's age, so the finding can be explained on its own.
+}
+
+impl MinorPolicy {
+    // Whether any rule is set at all. Without one, nobody's age matters here.
+    pub fn is_active(&self) -> bool {
+        !self.allowed_hours.is_empty() || self.require_guardian
+    }
+
+    // Some(true) for a minor, Some(false) for an adult, None when their age isn't known.
+    pub fn is_minor(&self, visitor: &Visitor, local: Timestamp) -> Option<bool> {
+        visitor
+            .age_on(local.date())
+            .map(|age| age < u32::from(self.age))
+    }
+
+    pub fn within_hours(&self, local: Timestamp) -> bool {
+        self.allowed_hours.is_empty()
+            || self.allowed_hours.iter().any(|hours| hours.contains(local))
+    }
+
+    // Everything about the visitor that breaks a rule, at local time, with who is inside right now.
+    pub fn check(
+        &self,
+        visitor: &Visitor,
+        local: Timestamp,
+        occupancy: &Occupancy,
+    ) -> Vec<MinorFinding> {
+        let mut findings = Vec::new();
+        if !self.is_active() {
+            return findings;
+        }
+        match (self.is_minor(visitor, local), self.unknown_age) {
+            (Some(false), _) => return findings,
+            (Some(true), _) => {}
+            (None, UnknownAge::AsMinor) => findings.push(MinorFinding {
+                issue: MinorIssue::AgeUnknown,
+                enforcement: Enforcement::Flag,
+                under: self.age,
+            }),
+            (None, UnknownAge::Flag) => {
+                findings.push(MinorFinding {
+                    issue: MinorIssue::AgeUnknown,
+                    enforcement: Enforcement::Flag,
+                    under: self.age,
+                });
+                return findings;
+            }
+            (None, UnknownAge::Deny) => {
+                findings.push(MinorFinding {
+                    issue: MinorIssue::AgeUnknown,
+                    enforcement: Enforcement::Deny,
+                    under: self.age,
+                });
+                return findings;
+            }
+        }
+
+        if !self.within_hours(local) {
+            findings.push(MinorFinding {
+                issue: MinorIssue::OutsideHours(self.allowed_hours.clone()),
+                enforcement: self.outside_hours,
+                under: self.age,
+            });
+        }
+        if self.require_guardian {
+            let issue = if visitor.guardians.is_empty() {
+                Some(MinorIssue::NoGuardian)
+            } else if !visitor.guardians.iter().any(|id| occupancy.is_inside(*id)) {
+                Some(MinorIssue::GuardianNotInside(visitor.guardians.clone()))
+            } else {
+                None
+            };
+            if let Some(issue) = issue {
+                findings.push(MinorFinding {
+                    issue,
+                    enforcement: Enforcement::Deny,
+                    under: self.age,
+                });
+            }
+        }
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Date;
+    use crate::VisitorAction;
+
+    fn at(time: &str) -> Timestamp {
+        Timestamp::parse(time).expect("test times are valid")
+    }
+
+    fn visitor(id: VisitorId, born: Option<Date>) -> Visitor {
+        let mut visitor = Visitor::new("Steve", "Hi", VisitorAction::Accept, born);
+        visitor.id = id;
+        visitor
+    }
+
+    fn curfew() -> MinorPolicy {
+        MinorPolicy {
+            allowed_hours: vec![Schedule::parse("daily 09:00-21:00").unwrap()],
+            ..MinorPolicy::default()
+        }
+    }
+
+    fn issues(
+        policy: &MinorPolicy,
+        visitor: &Visitor,
+        time: &str,
+        occupancy: &Occupancy,
+    ) -> Vec<MinorIssue> {
+        policy
+            .check(visitor, at(time), occupancy)
+            .into_iter()
+            .map(|finding| finding.issue)
+            .collect()
+    }
+
+    #[test]
+    fn the_curfew_only_applies_to_minors() {
+        let policy = curfew();
+        let occupancy = Occupancy::default();
+        let steve = visitor(2, Date::new(2011, 3, 1));
+        let bert = visitor(1, Date::new(1980, 3, 1));
+
+        assert!(issues(&policy, &steve, "2026-10-18T20:59:00Z", &occupancy).is_empty());
+        assert_eq!(
+            issues(&policy, &steve, "2026-10-18T21:00:00Z", &occupancy),
+            [MinorIssue::OutsideHours(policy.allowed_hours.clone())]
+        );
+        assert!(issues(&policy, &bert, "2026-10-18T23:00:00Z", &occupancy).is_empty());
+    }
+
+    #[test]
+    fn flagged_curfews_let_minors_in() {
+        let policy = MinorPolicy {
+            outside_hours: Enforcement::Flag,
+            ..curfew()
+        };
+        let steve = visitor(2, Date::new(2011, 3, 1));
+        let findings = policy.check(&steve, at("2026-10-18T23:00:00Z"), &Occupancy::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].enforcement, Enforcement::Flag);
+    }
+
+    #[test]
+    fn a_guardian_has_to_be_inside() {
+        let policy = MinorPolicy {
+            require_guardian: true,
+            ..MinorPolicy::default()
+        };
+        let mut occupancy = Occupancy::default();
+        let mut steve = visitor(2, Date::new(2011, 3, 1));
+        let noon = "2026-10-18T12:00:00Z";
+
+        assert_eq!(
+            issues(&policy, &steve, noon, &occupancy),
+            [MinorIssue::NoGuardian]
+        );
+        steve.guardians.push(1);
+        assert_eq!(
+            issues(&policy, &steve, noon, &occupancy),
+            [MinorIssue::GuardianNotInside(vec![1])]
+        );
+        occupancy.check_in(1, "Bert", at("2026-10-18T11:00:00Z"));
+        assert!(issues(&policy, &steve, noon, &occupancy).is_empty());
+    }
+
+    #[test]
+    fn unknown_ages() {
+        let stranger = visitor(3, None);
+        let occupancy = Occupancy::default();
+        let late = "2026-10-18T23:00:00Z";
+        let with = |unknown_age| MinorPolicy {
+            unknown_age,
+            ..curfew()
+        };
+
+        let flagged = with(UnknownAge::Flag).check(&stranger, at(late), &occupancy);
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].enforcement, Enforcement::Flag);
+
+        let denied = with(UnknownAge::Deny).check(&stranger, at(late), &occupancy);
+        assert_eq!(denied[0].enforcement, Enforcement::Deny);
+
+        assert_eq!(
+            issues(&with(UnknownAge::AsMinor), &stranger, late, &occupancy),
+            [
+                MinorIssue::AgeUnknown,
+                MinorIssue::OutsideHours(curfew().allowed_hours)
+            ]
+        );
+    }
+}
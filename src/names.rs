@@ -0,0 +1,58 @@
+// Names have two forms. The display name is what the visitor typed, tidied up, and is used
+// whenever we talk to or about them. The key is a normalized copy used only for matching,
+// so "Bert", "BERT" and " bert " all find the same visitor while the greeting still says "Bert".
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+// How loosely names are compared. Set in the [matching] section of the config file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NameMatching {
+    // When true "José" and "Jose" are the same name.
+    pub ignore_diacritics: bool,
+}
+
+// Trims the ends, turns every run of whitespace inside the name into one space,
+// and puts the characters into canonical (NFC) form. Capitalisation is kept.
+pub fn display_name(name: &str) -> String {
+    collapse_whitespace(&name.nfc().collect::<String>())
+}
+
+// The form of a name used for comparisons.
+//
+// NFKC folds look-alike characters into one form, e.g. full width "Ｂｅｒｔ" becomes "Bert"
+// and the "ﬁ" ligature becomes "fi". Case folding then makes upper and lower case equal.
+pub fn name_key(name: &str, matching: NameMatching) -> String {
+    let folded = case_fold(&name.nfkc().collect::<String>());
+    let key = if matching.ignore_diacritics {
+        // NFD splits "é" into "e" followed by a combining accent, which filter then drops.
+        folded
+            .nfd()
+            .filter(|c| !is_combining_mark(*c))
+            .nfc()
+            .collect()
+    } else {
+        folded
+    };
+    collapse_whitespace(&key)
+}
+
+// to_lowercase covers almost all of Unicode case folding. The few characters where folding
+// differs from lowercasing are patched up afterwards, so "STRASSE" and "straße" match.
+fn case_fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.to_lowercase().chars() {
+        match c {
+            'ß' | 'ẞ' => folded.push_str("ss"),
+            'ς' => folded.push('σ'), // Greek final sigma folds to the ordinary sigma.
+            'ſ' => folded.push('s'), // long s
+            _ => folded.push(c),
+        }
+    }
+    folded
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    // split_whitespace skips leading, trailing and repeated whitespace, join puts single spaces back.
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,386 @@
+// Who is inside the treehouse right now, and how long everybody stayed.
+//
+// Nothing about occupancy is stored on its own. Admitting someone writes a [checkin] entry to the
+// audit log (see audit.rs) and seeing them leave writes a [checkout] entry, so the state can always
+// be worked out again by reading the log from the start:
+//
+//     [checkin]
+//     time = 2026-10-18T14:05:00Z
+//     visitor = 2
+//     name = Steve
+//
+//     [checkout]
+//     time = 2026-10-18T16:35:00Z
+//     visitor = 2
+//     name = Steve
+//
+// name is the visitor's name at the time, so the history still reads well after they are removed.
+//
+// When the treehouse is full, accepted visitors wait in a queue instead. Joining it writes a [queued]
+// entry with the visitor's priority, and giving up their place writes an [unqueued] entry. Being
+// checked in takes them off the queue. The settings live in the [occupancy] section of the config file:
+//
+//     [occupancy]
+//     capacity = 8           # how many people fit inside, leave it out for no limit
+//     queue = members_first  # or fifo, the default: first come, first served
+//
+// With members_first, full members (accept and accept_with_note) wait ahead of probationary ones,
+// and within each group the queue is still first come, first served.
+
+use crate::clock::Timestamp;
+use crate::storage::{ParseError, Record};
+use crate::{VisitorAction, VisitorId};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueueOrder {
+    #[default]
+    Fifo,
+    MembersFirst,
+}
+
+impl QueueOrder {
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "fifo" => Some(QueueOrder::Fifo),
+            "members_first" => Some(QueueOrder::MembersFirst),
+            _ => None,
+        }
+    }
+
+    // Higher priorities are let in first.
+    pub fn priority(&self, action: &VisitorAction) -> u32 {
+        match (self, action) {
+            (
+                QueueOrder::MembersFirst,
+                VisitorAction::Accept | VisitorAction::AcceptWithNote { .. },
+            ) => 1,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OccupancySettings {
+    pub capacity: Option<usize>, // None means there is no limit.
+    pub queue_order: QueueOrder,
+}
+
+// Somebody waiting for a place inside.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Waiting {
+    pub visitor: VisitorId,
+    pub name: String,
+    pub since: Timestamp,
+    pub priority: u32,
+}
+
+// One visit from check-in to check-out. left is None while they are still inside.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stay {
+    pub visitor: VisitorId,
+    pub name: String,
+    pub arrived: Timestamp,
+    pub left: Option<Timestamp>,
+}
+
+impl Stay {
+    pub fn is_inside(&self) -> bool {
+        self.left.is_none()
+    }
+
+    // How long the stay lasted in seconds, or has lasted so far if they are still inside.
+    pub fn duration(&self, now: Timestamp) -> i64 {
+        self.left.unwrap_or(now).0 - self.arrived.0
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Occupancy {
+    stays: Vec<Stay>, // every stay in the order they arrived, finished ones included.
+    waiting: Vec<Waiting>, // the queue, next to be let in first.
+}
+
+pub fn checkin_entry(time: Timestamp, visitor: VisitorId, name: &str) -> Record {
+    movement_entry("checkin", time, visitor, name)
+}
+
+pub fn checkout_entry(time: Timestamp, visitor: VisitorId, name: &str) -> Record {
+    movement_entry("checkout", time, visitor, name)
+}
+
+pub fn queued_entry(time: Timestamp, visitor: VisitorId, name: &str, priority: u32) -> Record {
+    let mut entry = movement_entry("queued", time, visitor, name);
+    entry.push("priority", priority);
+    entry
+}
+
+pub fn unqueued_entry(time: Timestamp, visitor: VisitorId, name: &str) -> Record {
+    movement_entry("unqueued", time, visitor, name)
+}
+
+fn movement_entry(kind: &str, time: Timestamp, visitor: VisitorId, name: &str) -> Record {
+    let mut entry = Record::new(kind);
+    entry.push("time", time);
+    entry.push("visitor", visitor);
+    entry.push("name", name);
+    entry
+}
+
+impl Occupancy {
+    // Replays the check-ins, check-outs and queue changes in the audit log.
+    pub fn from_entries(entries: &[Record]) -> Result<Self, ParseError> {
+        let mut occupancy = Self::default();
+        for entry in entries {
+            occupancy.apply(entry)?;
+        }
+        Ok(occupancy)
+    }
+
+    // Updates the state with one audit log entry. Entries about anything else are skipped.
+    pub fn apply(&mut self, entry: &Record) -> Result<(), ParseError> {
+        if !["checkin", "checkout", "queued", "unqueued"].contains(&entry.kind.as_str()) {
+            return Ok(());
+        }
+        let error = |message: String| ParseError::new(entry.line, message);
+        let time = entry.require("time")?;
+        let time = Timestamp::parse(time).ok_or_else(|| error(format!("bad time `{}`", time)))?;
+        let visitor = entry.require("visitor")?;
+        let visitor = visitor
+            .parse()
+            .map_err(|_| error(format!("bad visitor id `{}`", visitor)))?;
+        let name = entry.require("name")?;
+
+        match entry.kind.as_str() {
+            "checkin" => self.check_in(visitor, name, time),
+            // A check-out for someone who isn't inside changes nothing, there is no stay to close.
+            "checkout" => {
+                self.check_out(visitor, time);
+            }
+            "queued" => {
+                let priority = entry.require("priority")?;
+                let priority = priority
+                    .parse()
+                    .map_err(|_| error(format!("bad priority `{}`", priority)))?;
+                self.enqueue(visitor, name, priority, time);
+            }
+            _ => {
+                self.dequeue(visitor);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_inside(&self, visitor: VisitorId) -> bool {
+        self.inside().any(|stay| stay.visitor == visitor)
+    }
+
+    // Everybody inside right now, earliest arrival first.
+    pub fn inside(&self) -> impl Iterator<Item = &Stay> {
+        self.stays.iter().filter(|stay| stay.is_inside())
+    }
+
+    pub fn count_inside(&self) -> usize {
+        self.inside().count()
+    }
+
+    pub fn stays(&self) -> &[Stay] {
+        &self.stays
+    }
+
+    // How many times the visitor has been let in, and when they last arrived.
+    pub fn history(&self, visitor: VisitorId) -> (usize, Option<Timestamp>) {
+        let mut count = 0;
+        let mut last = None;
+        for stay in self.stays.iter().filter(|stay| stay.visitor == visitor) {
+            count += 1;
+            last = Some(stay.arrived);
+        }
+        (count, last)
+    }
+
+    pub fn has_room(&self, settings: OccupancySettings) -> bool {
+        settings
+            .capacity
+            .is_none_or(|capacity| self.count_inside() < capacity)
+    }
+
+    pub fn waiting(&self) -> &[Waiting] {
+        &self.waiting
+    }
+
+    // Where the visitor is in the queue, counting from 1, or None if they aren't waiting.
+    pub fn queue_position(&self, visitor: VisitorId) -> Option<usize> {
+        self.waiting
+            .iter()
+            .position(|waiting| waiting.visitor == visitor)
+            .map(|index| index + 1)
+    }
+
+    // Puts the visitor behind everybody with the same or a higher priority and returns their position.
+    // Somebody already waiting keeps the place they have.
+    pub fn enqueue(
+        &mut self,
+        visitor: VisitorId,
+        name: &str,
+        priority: u32,
+        time: Timestamp,
+    ) -> usize {
+        if let Some(position) = self.queue_position(visitor) {
+            return position;
+        }
+        let index = self
+            .waiting
+            .iter()
+            .take_while(|waiting| waiting.priority >= priority)
+            .count();
+        self.waiting.insert(
+            index,
+            Waiting {
+                visitor,
+                name: name.to_string(),
+                since: time,
+                priority,
+            },
+        );
+        index + 1
+    }
+
+    // Takes the visitor off the queue and returns their place in it, or None if they weren't waiting.
+    pub fn dequeue(&mut self, visitor: VisitorId) -> Option<Waiting> {
+        let index = self.queue_position(visitor)? - 1;
+        Some(self.waiting.remove(index))
+    }
+
+    // Checking in someone who is already inside leaves their original arrival time alone.
+    // Anybody checked in stops waiting.
+    pub fn check_in(&mut self, visitor: VisitorId, name: &str, time: Timestamp) {
+        self.dequeue(visitor);
+        if !self.is_inside(visitor) {
+            self.stays.push(Stay {
+                visitor,
+                name: name.to_string(),
+                arrived: time,
+                left: None,
+            });
+        }
+    }
+
+    // Closes the visitor's open stay and returns it, or None if they weren't inside.
+    pub fn check_out(&mut self, visitor: VisitorId, time: Timestamp) -> Option<&Stay> {
+        let stay = self
+            .stays
+            .iter_mut()
+            .find(|stay| stay.visitor == visitor && stay.is_inside())?;
+        stay.left = Some(time);
+        Some(stay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> Timestamp {
+        Timestamp::parse(time).expect("test times are valid")
+    }
+
+    fn waiting_ids(occupancy: &Occupancy) -> Vec<VisitorId> {
+        occupancy
+            .waiting()
+            .iter()
+            .map(|waiting| waiting.visitor)
+            .collect()
+    }
+
+    #[test]
+    fn members_wait_ahead_of_probation() {
+        let order = QueueOrder::MembersFirst;
+        let member = order.priority(&VisitorAction::Accept);
+        let newcomer = order.priority(&VisitorAction::Probation);
+        assert!(member > newcomer);
+        assert_eq!(QueueOrder::Fifo.priority(&VisitorAction::Accept), 0);
+
+        let mut occupancy = Occupancy::default();
+        let now = at("2026-10-18T19:00:00Z");
+        assert_eq!(occupancy.enqueue(5, "May", newcomer, now), 1);
+        assert_eq!(occupancy.enqueue(1, "Bert", member, now), 1);
+        assert_eq!(occupancy.enqueue(2, "Steve", member, now), 2);
+        assert_eq!(occupancy.enqueue(6, "June", newcomer, now), 4);
+        assert_eq!(waiting_ids(&occupancy), [1, 2, 5, 6]);
+
+        // Asking again while still waiting keeps the place and the time they joined.
+        let later = at("2026-10-18T19:30:00Z");
+        assert_eq!(occupancy.enqueue(5, "May", newcomer, later), 3);
+        assert_eq!(occupancy.waiting()[2].since, now);
+    }
+
+    #[test]
+    fn still_full_until_somebody_leaves() {
+        let settings = OccupancySettings {
+            capacity: Some(2),
+            queue_order: QueueOrder::Fifo,
+        };
+        let mut occupancy = Occupancy::default();
+        let now = at("2026-10-18T19:00:00Z");
+        occupancy.check_in(1, "Bert", now);
+        occupancy.check_in(2, "Steve", now);
+        assert!(!occupancy.has_room(settings));
+        assert!(occupancy.has_room(OccupancySettings::default()));
+
+        occupancy.enqueue(5, "May", 0, now);
+        occupancy.enqueue(6, "June", 0, now);
+        assert!(!occupancy.has_room(settings));
+        assert_eq!(occupancy.queue_position(6), Some(2));
+
+        let stay = occupancy.check_out(1, at("2026-10-18T20:00:00Z")).unwrap();
+        assert_eq!(stay.duration(now), 3600);
+        assert!(occupancy.has_room(settings));
+
+        // Letting in the first in line moves everybody else up.
+        occupancy.check_in(5, "May", at("2026-10-18T20:00:00Z"));
+        assert!(!occupancy.has_room(settings));
+        assert_eq!(waiting_ids(&occupancy), [6]);
+        assert_eq!(occupancy.queue_position(5), None);
+    }
+
+    #[test]
+    fn leaving_the_queue() {
+        let mut occupancy = Occupancy::default();
+        let now = at("2026-10-18T19:00:00Z");
+        occupancy.enqueue(5, "May", 0, now);
+        occupancy.enqueue(6, "June", 0, now);
+
+        let may = occupancy.dequeue(5).unwrap();
+        assert_eq!((may.visitor, may.name.as_str()), (5, "May"));
+        assert_eq!(occupancy.dequeue(5), None);
+        assert_eq!(occupancy.queue_position(6), Some(1));
+        assert_eq!(occupancy.check_out(6, now), None);
+    }
+
+    #[test]
+    fn the_queue_is_read_back_from_the_log() {
+        let now = at("2026-10-18T19:00:00Z");
+        let entries = [
+            checkin_entry(now, 1, "Bert"),
+            queued_entry(now, 5, "May", 0),
+            queued_entry(now, 2, "Steve", 1),
+            queued_entry(now, 6, "June", 0),
+            unqueued_entry(now, 6, "June"),
+            checkout_entry(now, 1, "Bert"),
+            checkin_entry(now, 2, "Steve"),
+        ];
+        let occupancy = Occupancy::from_entries(&entries).unwrap();
+        assert_eq!(waiting_ids(&occupancy), [5]);
+        assert!(occupancy.is_inside(2));
+        assert!(!occupancy.is_inside(1));
+        assert_eq!(occupancy.history(1), (1, Some(now)));
+
+        let mut bad = unqueued_entry(now, 5, "May");
+        bad.kind = "queued".to_string();
+        bad.push("priority", "high");
+        assert!(Occupancy::from_entries(&[bad])
+            .unwrap_err()
+            .message
+            .contains("high"));
+    }
+}
@@ -0,0 +1,171 @@
+// Guest passes: a member lets somebody in for a limited time without putting them on the list for good.
+//
+//     rust-treehouse issue-pass Bert "Aunt May" --from 2026-10-24 --until 2026-10-25 --uses 2
+//
+// The guest is stored like any other visitor (see storage.rs) so the door, the logs and every other
+// rule work for them as usual, but they also carry the pass:
+//
+//     pass_issued_by = 1
+//     pass_from = 2026-10-23T23:00:00Z
+//     pass_until = 2026-10-25T23:00:00Z
+//     pass_uses = 2
+//
+// pass_from and pass_until are UTC times, worked out from the local dates given when the pass was
+// issued, with the until date included. pass_uses is left out when the pass can be used any number
+// of times. A use is a check-in, and check-ins are already in the audit log, so the count can't go
+// wrong if the visitor list is restored from a backup.
+//
+// A pass isn't deleted when it lapses. The guest is turned away with the reason, which is not the same
+// as being refused, and a member can issue them a new pass.
+
+use crate::clock::{Date, Timestamp, UtcOffset, SECONDS_PER_DAY};
+use crate::occupancy::Occupancy;
+use crate::VisitorId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuestPass {
+    pub issued_by: VisitorId,
+    pub valid_from: Timestamp,
+    pub valid_until: Timestamp, // the first moment the pass no longer works.
+    pub max_uses: Option<u32>,
+}
+
+// Why a pass can't be used right now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lapse {
+    NotYetValid(Timestamp),
+    Expired(Timestamp),
+    UsedUp(u32),
+}
+
+// What the door found when it looked at a guest's pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassStatus {
+    // uses counts this visit too.
+    Valid { pass: GuestPass, uses: u32 },
+    Lapsed { pass: GuestPass, lapse: Lapse },
+}
+
+impl GuestPass {
+    // A pass for whole local days, from the start of from to the end of until.
+    pub fn for_days(
+        issued_by: VisitorId,
+        from: Date,
+        until: Date,
+        max_uses: Option<u32>,
+        offset: UtcOffset,
+    ) -> Self {
+        // Local midnight is earlier in UTC when the treehouse is ahead of it.
+        let utc = |date: Date| Timestamp(date.start().0 - offset.0);
+        Self {
+            issued_by,
+            valid_from: utc(from),
+            valid_until: Timestamp(utc(until).0 + SECONDS_PER_DAY),
+            max_uses,
+        }
+    }
+
+    // How many times the guest has been let in on this pass so far.
+    pub fn uses(&self, visitor: VisitorId, occupancy: &Occupancy) -> u32 {
+        occupancy
+            .stays()
+            .iter()
+            .filter(|stay| stay.visitor == visitor && stay.arrived >= self.valid_from)
+            .count() as u32
+    }
+
+    pub fn status(&self, visitor: VisitorId, now: Timestamp, occupancy: &Occupancy) -> PassStatus {
+        let uses = self.uses(visitor, occupancy);
+        let lapse = if now < self.valid_from {
+            Some(Lapse::NotYetValid(self.valid_from))
+        } else if now >= self.valid_until {
+            Some(Lapse::Expired(self.valid_until))
+        } else {
+            self.max_uses.filter(|max| uses >= *max).map(Lapse::UsedUp)
+        };
+        match lapse {
+            Some(lapse) => PassStatus::Lapsed { pass: *self, lapse },
+            None => PassStatus::Valid {
+                pass: *self,
+                uses: uses + 1,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> Timestamp {
+        Timestamp::parse(time).expect("test times are valid")
+    }
+
+    fn pass(max_uses: Option<u32>) -> GuestPass {
+        GuestPass::for_days(
+            1,
+            Date::new(2026, 10, 18).unwrap(),
+            Date::new(2026, 10, 19).unwrap(),
+            max_uses,
+            UtcOffset(3600),
+        )
+    }
+
+    #[test]
+    fn a_pass_covers_whole_local_days() {
+        let pass = pass(None);
+        assert_eq!(pass.valid_from, at("2026-10-17T23:00:00Z"));
+        assert_eq!(pass.valid_until, at("2026-10-19T23:00:00Z"));
+    }
+
+    #[test]
+    fn a_pass_lapses_by_date() {
+        let pass = pass(None);
+        let occupancy = Occupancy::default();
+        let status = |time: &str| pass.status(5, at(time), &occupancy);
+
+        assert_eq!(
+            status("2026-10-17T22:59:59Z"),
+            PassStatus::Lapsed {
+                pass,
+                lapse: Lapse::NotYetValid(pass.valid_from)
+            }
+        );
+        assert_eq!(
+            status("2026-10-18T12:00:00Z"),
+            PassStatus::Valid { pass, uses: 1 }
+        );
+        assert_eq!(
+            status("2026-10-19T23:00:00Z"),
+            PassStatus::Lapsed {
+                pass,
+                lapse: Lapse::Expired(pass.valid_until)
+            }
+        );
+    }
+
+    #[test]
+    fn a_pass_lapses_by_uses() {
+        let pass = pass(Some(2));
+        let mut occupancy = Occupancy::default();
+        // A visit before the pass was issued doesn't use it up.
+        occupancy.check_in(5, "May", at("2026-10-10T12:00:00Z"));
+        occupancy.check_out(5, at("2026-10-10T13:00:00Z"));
+        occupancy.check_in(5, "May", at("2026-10-18T12:00:00Z"));
+        occupancy.check_out(5, at("2026-10-18T13:00:00Z"));
+
+        let later = at("2026-10-18T18:00:00Z");
+        assert_eq!(
+            pass.status(5, later, &occupancy),
+            PassStatus::Valid { pass, uses: 2 }
+        );
+        occupancy.check_in(5, "May", later);
+        assert_eq!(
+            pass.status(5, later, &occupancy),
+            PassStatus::Lapsed {
+                pass,
+                lapse: Lapse::UsedUp(2)
+            }
+        );
+    }
+}
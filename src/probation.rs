@@ -0,0 +1,304 @@
+// Probation that ends: visitors on probation are promoted to accept once they have proved themselves,
+// or refused when their probation runs out first.
+//
+// What it takes is set in the [probation] section of the config file. Every setting is optional:
+//
+//     [probation]
+//     visits = 3          # times they have been let in since their probation started
+//     days = 14           # days since their probation started
+//     approvals = 2       # members who approved them with `approve <name> <member>`
+//     expire_after = 60   # days after which anybody still on probation is refused
+//
+// A visitor is promoted when they meet every one of visits, days and approvals that is set. Without any
+// of them nobody is promoted automatically, as before. Without expire_after probation never runs out.
+//
+// The start of a visitor's probation and the members who approved them are stored with them (see storage.rs):
+//
+//     probation_since = 2026-10-18T19:30:00Z
+//     approved_by = 1
+//
+// Probation is reviewed whenever the visitor comes to the door, and for everybody at once with
+// `rust-treehouse probation`. Every change is written to the audit log (see audit.rs):
+//
+//     [probation]
+//     time = 2026-11-01T18:00:00Z
+//     visitor = 5
+//     name = Aunt May
+//     event = promoted
+//     visits = 3
+//     days = 14
+//     approvals = 2
+//
+// event is started, promoted, expired or ended. ended is an admin giving the visitor another action with
+// `set-action`, that entry has the new action instead of the progress:
+//
+//     [probation]
+//     time = 2026-11-01T18:00:00Z
+//     visitor = 5
+//     name = Aunt May
+//     event = ended
+//     action = accept
+//
+// Admins putting somebody on probation start it like the door does. Approvals are admin commands, so they
+// are logged as those.
+// Visitors who were put on probation before there was a start date get one the first time they are reviewed.
+
+use crate::clock::{Timestamp, SECONDS_PER_DAY};
+use crate::occupancy::Occupancy;
+use crate::storage::Record;
+use crate::{Visitor, VisitorAction};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProbationPolicy {
+    pub visits: Option<u32>,
+    pub days: Option<u32>,
+    pub approvals: Option<u32>,
+    pub expire_after: Option<u32>, // in days.
+}
+
+// How far a visitor on probation has got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub since: Timestamp,
+    pub visits: u32,
+    pub days: u32,
+    pub approvals: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    Started,
+    Promoted(Progress),
+    Expired(Progress),
+    Ended, // by an admin, who set the new action themselves.
+}
+
+impl Transition {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Transition::Started => "started",
+            Transition::Promoted(_) => "promoted",
+            Transition::Expired(_) => "expired",
+            Transition::Ended => "ended",
+        }
+    }
+}
+
+impl Progress {
+    // None for visitors who aren't on probation, or whose probation hasn't got a start date yet.
+    pub fn of(visitor: &Visitor, now: Timestamp, occupancy: &Occupancy) -> Option<Self> {
+        if visitor.action != VisitorAction::Probation {
+            return None;
+        }
+        let since = visitor.probation_since?;
+        let visits = occupancy
+            .stays()
+            .iter()
+            .filter(|stay| stay.visitor == visitor.id && stay.arrived >= since)
+            .count() as u32;
+        Some(Self {
+            since,
+            visits,
+            days: u32::try_from((now.0 - since.0) / SECONDS_PER_DAY).unwrap_or(0),
+            approvals: visitor.approved_by.len() as u32,
+        })
+    }
+}
+
+impl ProbationPolicy {
+    // Whether anything at all can promote a visitor.
+    pub fn promotes(&self) -> bool {
+        self.visits.is_some() || self.days.is_some() || self.approvals.is_some()
+    }
+
+    // Every criterion that is set has been met.
+    pub fn is_met(&self, progress: &Progress) -> bool {
+        let met = |needed: Option<u32>, got: u32| needed.is_none_or(|needed| got >= needed);
+        self.promotes()
+            && met(self.visits, progress.visits)
+            && met(self.days, progress.days)
+            && met(self.approvals, progress.approvals)
+    }
+
+    // What should happen to the visitor now, if anything. Promotion wins when both are due on the same day.
+    pub fn review(
+        &self,
+        visitor: &Visitor,
+        now: Timestamp,
+        occupancy: &Occupancy,
+    ) -> Option<Transition> {
+        if visitor.action != VisitorAction::Probation {
+            return None;
+        }
+        let Some(progress) = Progress::of(visitor, now, occupancy) else {
+            return Some(Transition::Started);
+        };
+        if self.is_met(&progress) {
+            Some(Transition::Promoted(progress))
+        } else if self
+            .expire_after
+            .is_some_and(|expire_after| progress.days >= expire_after)
+        {
+            Some(Transition::Expired(progress))
+        } else {
+            None
+        }
+    }
+}
+
+// Changes the visitor to match the transition. The audit entry is made separately, see entry.
+pub fn apply(visitor: &mut Visitor, transition: Transition, now: Timestamp) {
+    match transition {
+        Transition::Started => visitor.probation_since = Some(now),
+        Transition::Promoted(_) => end(visitor, VisitorAction::Accept),
+        Transition::Expired(_) => end(visitor, VisitorAction::Refuse),
+        Transition::Ended => end(visitor, visitor.action.clone()),
+    }
+}
+
+// Leaves probation for another action, forgetting when it started and who approved it.
+// The audit log still has both.
+pub fn end(visitor: &mut Visitor, action: VisitorAction) {
+    visitor.action = action;
+    visitor.probation_since = None;
+    visitor.approved_by.clear();
+}
+
+pub fn entry(time: Timestamp, visitor: &Visitor, transition: Transition) -> Record {
+    let mut entry = Record::new("probation");
+    entry.push("time", time);
+    entry.push("visitor", visitor.id);
+    entry.push("name", &visitor.name);
+    entry.push("event", transition.label());
+    if let Transition::Promoted(progress) | Transition::Expired(progress) = transition {
+        entry.push("visits", progress.visits);
+        entry.push("days", progress.days);
+        entry.push("approvals", progress.approvals);
+    }
+    if transition == Transition::Ended {
+        entry.push("action", visitor.action.label());
+    }
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> Timestamp {
+        Timestamp::parse(time).expect("test times are valid")
+    }
+
+    fn newcomer(since: Option<&str>) -> Visitor {
+        let mut visitor = Visitor::probationary("Aunt May", None);
+        visitor.id = 5;
+        visitor.probation_since = since.map(at);
+        visitor
+    }
+
+    // Lets the visitor in once a day for visits days, starting the day after their probation started.
+    fn visits(count: u32) -> Occupancy {
+        let mut occupancy = Occupancy::default();
+        for day in 0..i64::from(count) {
+            let arrived = Timestamp(at("2026-10-02T18:00:00Z").0 + day * SECONDS_PER_DAY);
+            occupancy.check_in(5, "Aunt May", arrived);
+            occupancy.check_out(5, Timestamp(arrived.0 + 3600));
+        }
+        occupancy
+    }
+
+    #[test]
+    fn promoted_when_every_criterion_is_met() {
+        let policy = ProbationPolicy {
+            visits: Some(3),
+            approvals: Some(1),
+            ..ProbationPolicy::default()
+        };
+        let mut may = newcomer(Some("2026-10-01T12:00:00Z"));
+        let now = at("2026-10-18T12:00:00Z");
+
+        assert_eq!(policy.review(&may, now, &visits(3)), None);
+        may.approved_by.push(1);
+        assert_eq!(policy.review(&may, now, &visits(2)), None);
+
+        let Some(transition) = policy.review(&may, now, &visits(3)) else {
+            panic!("three visits and an approval should be enough");
+        };
+        assert_eq!(
+            transition,
+            Transition::Promoted(Progress {
+                since: at("2026-10-01T12:00:00Z"),
+                visits: 3,
+                days: 17,
+                approvals: 1
+            })
+        );
+        apply(&mut may, transition, now);
+        assert_eq!(may.action, VisitorAction::Accept);
+        assert_eq!(may.probation_since, None);
+        assert!(may.approved_by.is_empty());
+    }
+
+    #[test]
+    fn refused_when_probation_runs_out() {
+        let policy = ProbationPolicy {
+            visits: Some(10),
+            expire_after: Some(30),
+            ..ProbationPolicy::default()
+        };
+        let mut may = newcomer(Some("2026-10-01T12:00:00Z"));
+        assert_eq!(
+            policy.review(&may, at("2026-10-31T11:59:59Z"), &visits(2)),
+            None
+        );
+
+        let now = at("2026-10-31T12:00:00Z");
+        let transition = policy.review(&may, now, &visits(2)).unwrap();
+        assert!(matches!(transition, Transition::Expired(progress) if progress.days == 30));
+        apply(&mut may, transition, now);
+        assert_eq!(may.action, VisitorAction::Refuse);
+    }
+
+    #[test]
+    fn without_criteria_nobody_is_promoted() {
+        let policy = ProbationPolicy::default();
+        let may = newcomer(Some("2020-01-01T00:00:00Z"));
+        assert!(!policy.promotes());
+        assert_eq!(
+            policy.review(&may, at("2026-10-18T12:00:00Z"), &visits(50)),
+            None
+        );
+    }
+
+    #[test]
+    fn probation_without_a_start_is_started() {
+        let policy = ProbationPolicy::default();
+        let mut may = newcomer(None);
+        let now = at("2026-10-18T12:00:00Z");
+        assert_eq!(
+            policy.review(&may, now, &Occupancy::default()),
+            Some(Transition::Started)
+        );
+        apply(&mut may, Transition::Started, now);
+        assert_eq!(may.probation_since, Some(now));
+
+        may.action = VisitorAction::Accept;
+        assert_eq!(policy.review(&may, now, &Occupancy::default()), None);
+    }
+
+    #[test]
+    fn ended_by_an_admin() {
+        let mut may = newcomer(Some("2026-10-01T12:00:00Z"));
+        may.approved_by.push(1);
+        may.action = VisitorAction::Refuse;
+        apply(&mut may, Transition::Ended, at("2026-10-18T12:00:00Z"));
+        assert_eq!(may.action, VisitorAction::Refuse);
+        assert_eq!(may.probation_since, None);
+        assert!(may.approved_by.is_empty());
+
+        let entry = entry(at("2026-10-18T12:00:00Z"), &may, Transition::Ended);
+        assert_eq!(entry.get("event"), Some("ended"));
+        assert_eq!(entry.get("action"), Some("refuse"));
+        assert_eq!(entry.get("visits"), None);
+    }
+}
@@ -0,0 +1,466 @@
+use std::fmt;
+
+use crate::names::{display_name, name_key, NameMatching};
+use crate::template::{self, TemplateError};
+use crate::{Visitor, VisitorAction, VisitorId};
+
+// The registry wraps the Vec<Visitor> that main used to own directly.
+// Keeping the vector private means every change goes through the methods below,
+// so rules such as "every visitor has their own id" are checked in one place.
+#[derive(Debug)]
+pub struct VisitorRegistry {
+    visitors: Vec<Visitor>,
+    next_id: VisitorId,
+    matching: NameMatching,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RegistryError {
+    NotFound(String),
+    UnknownId(VisitorId),
+    // More than one visitor has this name, the ids tell them apart.
+    Ambiguous(String, Vec<VisitorId>),
+    // An alias has to point at one visitor only, so it can't match anybody else's name or alias.
+    AliasTaken { alias: String, owner: VisitorId },
+    EmptyName,
+    BadGreeting(TemplateError),
+    // Every id has been handed out. Ids are never reused, see next_id.
+    NoIdsLeft,
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegistryError::NotFound(name) => write!(f, "{} is not on the visitor list", name),
+            RegistryError::UnknownId(id) => write!(f, "there is no visitor #{}", id),
+            RegistryError::Ambiguous(name, ids) => {
+                let ids: Vec<String> = ids.iter().map(|id| format!("#{}", id)).collect();
+                write!(
+                    f,
+                    "{} matches more than one visitor ({}), use the #id instead",
+                    name,
+                    ids.join(", ")
+                )
+            }
+            RegistryError::AliasTaken { alias, owner } => {
+                write!(f, "{} is already used by visitor #{}", alias, owner)
+            }
+            RegistryError::EmptyName => write!(f, "a name can't be empty"),
+            RegistryError::BadGreeting(error) => write!(f, "bad greeting: {}", error),
+            RegistryError::NoIdsLeft => write!(f, "there are no visitor ids left to hand out"),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+// The result of looking a name up. Two people can share a name, so "found" isn't always one visitor.
+#[derive(Debug)]
+pub enum Lookup<'a> {
+    NotFound,
+    Found(&'a Visitor),
+    Ambiguous(Vec<&'a Visitor>),
+}
+
+impl VisitorRegistry {
+    pub fn new() -> Self {
+        Self {
+            visitors: Vec::new(),
+            next_id: 1,
+            matching: NameMatching::default(),
+        }
+    }
+
+    pub fn name_matching(&self) -> NameMatching {
+        self.matching
+    }
+
+    // Aliases were checked with the old matching when they were added. Names that were different before
+    // can be the same now, e.g. "José" and "Jose" once diacritics are ignored, so every alias is checked
+    // again just like add_alias would. On a clash nothing changes and the alias is named in the error.
+    pub fn set_name_matching(&mut self, matching: NameMatching) -> Result<(), RegistryError> {
+        // Names may be shared, so they are all taken first and only the aliases have to be unique.
+        let mut taken: Vec<(String, VisitorId)> = self
+            .visitors
+            .iter()
+            .map(|visitor| (name_key(&visitor.name, matching), visitor.id))
+            .collect();
+        for visitor in &self.visitors {
+            for alias in &visitor.aliases {
+                let key = name_key(alias, matching);
+                if let Some((_, owner)) = taken.iter().find(|(other, _)| *other == key) {
+                    return Err(RegistryError::AliasTaken {
+                        alias: alias.clone(),
+                        owner: *owner,
+                    });
+                }
+                taken.push((key, visitor.id));
+            }
+        }
+        self.matching = matching;
+        Ok(())
+    }
+
+    // Builds a registry from an existing list, e.g. one loaded from disk.
+    // Visitors without an id (id 0) are given the next free one.
+    pub fn from_visitors(visitors: Vec<Visitor>) -> Self {
+        let mut registry = Self::new();
+        for visitor in visitors {
+            registry.insert(visitor);
+        }
+        registry
+    }
+
+    // The id the next new visitor will get. Ids are never handed out twice,
+    // so the counter is saved with the list rather than worked out from the visitors still on it.
+    pub fn next_id(&self) -> VisitorId {
+        self.next_id
+    }
+
+    pub fn set_next_id(&mut self, next_id: VisitorId) {
+        // max makes sure the counter never goes backwards past an id that is already in use.
+        self.next_id = self.next_id.max(next_id);
+    }
+
+    // Puts back a visitor who already has an id, e.g. one read from disk.
+    // A visitor without an id, or with one that is already taken, is given a fresh id instead.
+    pub fn restore(&mut self, visitor: Visitor) -> VisitorId {
+        self.insert(visitor)
+    }
+
+    fn insert(&mut self, mut visitor: Visitor) -> VisitorId {
+        if visitor.id == 0 || self.get(visitor.id).is_some() {
+            visitor.id = self.next_id;
+        }
+        // saturating_add stops at the largest id instead of overflowing, add refuses to go past it.
+        self.next_id = self.next_id.max(visitor.id.saturating_add(1));
+        let id = visitor.id;
+        self.visitors.push(visitor);
+        id
+    }
+
+    // Adds a visitor and returns the id they were given. Names don't have to be unique,
+    // but any aliases the visitor arrives with are checked just like add_alias would.
+    // The greeting has to be a valid template, see template.rs.
+    pub fn add(&mut self, mut visitor: Visitor) -> Result<VisitorId, RegistryError> {
+        template::validate(&visitor.greeting).map_err(RegistryError::BadGreeting)?;
+        if self.next_id == VisitorId::MAX {
+            return Err(RegistryError::NoIdsLeft);
+        }
+        // New visitors always get a fresh id.
+        visitor.id = 0;
+        // take swaps an empty vector into the field and hands back what was there.
+        let aliases = std::mem::take(&mut visitor.aliases);
+        // The id only counts as handed out once the aliases are accepted too.
+        let next_id = self.next_id;
+        let id = self.insert(visitor);
+        for alias in aliases {
+            if let Err(error) = self.add_alias(id, &alias) {
+                // insert pushed the visitor last, so this undoes the add.
+                self.visitors.pop();
+                self.next_id = next_id;
+                return Err(error);
+            }
+        }
+        Ok(id)
+    }
+
+    // Which visitor, other than `except`, already answers to this key.
+    fn key_owner(&self, key: &str, except: VisitorId) -> Option<VisitorId> {
+        self.visitors
+            .iter()
+            .filter(|visitor| visitor.id != except)
+            .find(|visitor| visitor.keys(self.matching).any(|other| other == key))
+            .map(|visitor| visitor.id)
+    }
+
+    pub fn add_alias(&mut self, id: VisitorId, alias: &str) -> Result<(), RegistryError> {
+        let alias = display_name(alias);
+        let key = name_key(&alias, self.matching);
+        if key.is_empty() {
+            return Err(RegistryError::EmptyName);
+        }
+        let visitor = self.get(id).ok_or(RegistryError::UnknownId(id))?;
+        // The visitor already answers to this, through their name or another alias.
+        if visitor.keys(self.matching).any(|existing| existing == key) {
+            return Err(RegistryError::AliasTaken { alias, owner: id });
+        }
+        if let Some(owner) = self.key_owner(&key, id) {
+            return Err(RegistryError::AliasTaken { alias, owner });
+        }
+        self.get_mut(id)
+            .expect("visitor was found above")
+            .aliases
+            .push(alias);
+        Ok(())
+    }
+
+    // Returns the alias as it was stored.
+    pub fn remove_alias(&mut self, id: VisitorId, alias: &str) -> Result<String, RegistryError> {
+        let matching = self.matching;
+        let key = name_key(alias, matching);
+        let visitor = self.get_mut(id).ok_or(RegistryError::UnknownId(id))?;
+        let index = visitor
+            .aliases
+            .iter()
+            .position(|existing| name_key(existing, matching) == key)
+            .ok_or_else(|| RegistryError::NotFound(alias.trim().to_string()))?;
+        Ok(visitor.aliases.remove(index))
+    }
+
+    // Anybody who had the removed visitor as a guardian, sponsor or approver loses the link,
+    // so it can't point at a stranger. The audit log still says who vouched and approved.
+    pub fn remove(&mut self, id: VisitorId) -> Result<Visitor, RegistryError> {
+        // position works like find, but returns the index of the match instead of the match itself.
+        let index = self
+            .visitors
+            .iter()
+            .position(|visitor| visitor.id == id)
+            .ok_or(RegistryError::UnknownId(id))?;
+        for visitor in &mut self.visitors {
+            visitor.guardians.retain(|guardian| *guardian != id);
+            visitor.approved_by.retain(|member| *member != id);
+            if visitor.sponsor == Some(id) {
+                visitor.sponsor = None;
+            }
+        }
+        Ok(self.visitors.remove(index))
+    }
+
+    pub fn get(&self, id: VisitorId) -> Option<&Visitor> {
+        self.visitors.iter().find(|visitor| visitor.id == id)
+    }
+
+    // iter_mut is the mutable twin of iter, it hands out &mut Visitor so the match can be changed in place.
+    pub fn get_mut(&mut self, id: VisitorId) -> Option<&mut Visitor> {
+        self.visitors.iter_mut().find(|visitor| visitor.id == id)
+    }
+
+    // Every visitor whose name or alias matches, in the order they were added.
+    pub fn find_by_name(&self, name: &str) -> Vec<&Visitor> {
+        let key = name_key(name, self.matching);
+
+        // Iterators can do a lot, they are designed around function chaining.
+        // Each iterator step works as a building block to massage the data from the previous step into what you need.
+        // iterators are very fast, often faster than writing loops as the compiler can be certain you arent
+        // doing anything dangerous like trying to read beyond the end of an array so it can make many optimisations.
+        self.visitors
+            .iter() // create an iterator that contains all the data in the visitor list
+            .filter(|visitor| visitor.keys(self.matching).any(|k| k == key)) // filter runs a closure and keeps every value it returns true for.
+            // Closures are used a lot on Rust. Closures capture data from the scope in which they are called.
+            .collect() // collect gathers what is left into a new collection, here a Vec.
+    }
+
+    pub fn lookup(&self, name: &str) -> Lookup<'_> {
+        let mut matches = self.find_by_name(name);
+        match matches.len() {
+            0 => Lookup::NotFound,
+            1 => Lookup::Found(matches.remove(0)),
+            _ => Lookup::Ambiguous(matches),
+        }
+    }
+
+    // Turns what an admin typed into a single visitor id.
+    // `#12` picks visitor 12, anything else is a name which has to match exactly one visitor.
+    pub fn resolve(&self, selector: &str) -> Result<VisitorId, RegistryError> {
+        if let Some(id) = selector.strip_prefix('#') {
+            if let Ok(id) = id.parse::<VisitorId>() {
+                return self
+                    .get(id)
+                    .map(|visitor| visitor.id)
+                    .ok_or(RegistryError::UnknownId(id));
+            }
+        }
+        match self.lookup(selector) {
+            Lookup::Found(visitor) => Ok(visitor.id),
+            Lookup::NotFound => Err(RegistryError::NotFound(selector.trim().to_string())),
+            Lookup::Ambiguous(visitors) => Err(RegistryError::Ambiguous(
+                selector.trim().to_string(),
+                visitors.iter().map(|visitor| visitor.id).collect(),
+            )),
+        }
+    }
+
+    // Returns the action the visitor had before the update.
+    pub fn update_action(
+        &mut self,
+        id: VisitorId,
+        action: VisitorAction,
+    ) -> Result<VisitorAction, RegistryError> {
+        let visitor = self.get_mut(id).ok_or(RegistryError::UnknownId(id))?;
+        // std::mem::replace swaps the new value in and gives back the old one, without cloning.
+        Ok(std::mem::replace(&mut visitor.action, action))
+    }
+
+    // Returns the old greeting. A greeting that isn't a valid template is refused and nothing changes.
+    pub fn update_greeting(
+        &mut self,
+        id: VisitorId,
+        greeting: &str,
+    ) -> Result<String, RegistryError> {
+        template::validate(greeting).map_err(RegistryError::BadGreeting)?;
+        let visitor = self.get_mut(id).ok_or(RegistryError::UnknownId(id))?;
+        Ok(std::mem::replace(
+            &mut visitor.greeting,
+            greeting.to_string(),
+        ))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Visitor> {
+        self.visitors.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.visitors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visitors.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[Visitor] {
+        &self.visitors
+    }
+}
+
+impl Default for VisitorRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Implementing IntoIterator for a reference lets callers write `for visitor in &registry`.
+impl<'a> IntoIterator for &'a VisitorRegistry {
+    type Item = &'a Visitor;
+    type IntoIter = std::slice::Iter<'a, Visitor>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VisitorAction;
+
+    fn registry() -> VisitorRegistry {
+        let mut registry = VisitorRegistry::new();
+        for name in ["Bert", "Steve", "Steve"] {
+            registry
+                .add(Visitor::new(name, "Hi", VisitorAction::Accept, None))
+                .unwrap();
+        }
+        registry
+    }
+
+    #[test]
+    fn a_shared_name_is_ambiguous() {
+        let registry = registry();
+        match registry.lookup("steve") {
+            Lookup::Ambiguous(visitors) => {
+                let ids: Vec<VisitorId> = visitors.iter().map(|visitor| visitor.id).collect();
+                assert_eq!(ids, [2, 3]);
+            }
+            other => panic!("expected two Steves, got {:?}", other),
+        }
+        assert!(matches!(
+            registry.resolve("Steve"),
+            Err(RegistryError::Ambiguous(_, ids)) if ids == [2, 3]
+        ));
+        assert_eq!(registry.resolve("#3"), Ok(3));
+        assert!(matches!(registry.lookup("  BERT "), Lookup::Found(bert) if bert.id == 1));
+        assert!(matches!(registry.lookup("Fred"), Lookup::NotFound));
+    }
+
+    #[test]
+    fn aliases_belong_to_one_visitor() {
+        let mut registry = registry();
+        registry.add_alias(2, "Stevo").unwrap();
+        assert!(matches!(registry.lookup("stevo"), Lookup::Found(steve) if steve.id == 2));
+        assert_eq!(
+            registry.add_alias(3, "Stevo"),
+            Err(RegistryError::AliasTaken {
+                alias: "Stevo".to_string(),
+                owner: 2
+            })
+        );
+        assert_eq!(
+            registry.add_alias(3, "Bert"),
+            Err(RegistryError::AliasTaken {
+                alias: "Bert".to_string(),
+                owner: 1
+            })
+        );
+    }
+
+    #[test]
+    fn ids_are_never_handed_out_twice() {
+        let mut registry = registry();
+        registry.remove(3).unwrap();
+        let id = registry
+            .add(Visitor::new("Fred", "Hi", VisitorAction::Refuse, None))
+            .unwrap();
+        assert_eq!(id, 4);
+        assert_eq!(registry.remove(3).unwrap_err(), RegistryError::UnknownId(3));
+
+        // A visitor turned away for their aliases doesn't use up an id.
+        let mut bertie = Visitor::new("Bertie", "Hi", VisitorAction::Accept, None);
+        bertie.aliases = vec!["Bertie B".to_string(), "Steve".to_string()];
+        assert!(matches!(
+            registry.add(bertie),
+            Err(RegistryError::AliasTaken { owner: 2, .. })
+        ));
+        assert_eq!(registry.next_id(), 5);
+        assert!(matches!(registry.lookup("Bertie B"), Lookup::NotFound));
+
+        registry.set_next_id(VisitorId::MAX);
+        let last = Visitor::new("Last", "Hi", VisitorAction::Accept, None);
+        assert_eq!(registry.add(last), Err(RegistryError::NoIdsLeft));
+    }
+
+    #[test]
+    fn removing_a_visitor_unlinks_them_from_everybody() {
+        let mut registry = registry();
+        let mut may = Visitor::probationary("Aunt May", None);
+        may.sponsor = Some(1);
+        may.approved_by = vec![1, 2];
+        may.guardians = vec![1];
+        let may = registry.add(may).unwrap();
+
+        registry.remove(1).unwrap();
+        let may = registry.get(may).unwrap();
+        assert_eq!(may.sponsor, None);
+        assert_eq!(may.approved_by, [2]);
+        assert!(may.guardians.is_empty());
+    }
+
+    #[test]
+    fn aliases_are_checked_again_when_matching_changes() {
+        let ignore_diacritics = NameMatching {
+            ignore_diacritics: true,
+        };
+        let mut registry = registry();
+        registry.add_alias(2, "Jose").unwrap();
+        registry.add_alias(3, "José").unwrap();
+        assert_eq!(
+            registry.set_name_matching(ignore_diacritics),
+            Err(RegistryError::AliasTaken {
+                alias: "José".to_string(),
+                owner: 2
+            })
+        );
+        assert_eq!(registry.name_matching(), NameMatching::default());
+
+        registry.remove_alias(3, "José").unwrap();
+        registry.add_alias(1, "Bért").unwrap();
+        assert!(matches!(
+            registry.set_name_matching(ignore_diacritics),
+            Err(RegistryError::AliasTaken { owner: 1, .. })
+        ));
+
+        registry.remove_alias(1, "Bért").unwrap();
+        registry.set_name_matching(ignore_diacritics).unwrap();
+        assert!(matches!(registry.lookup("JOSÉ"), Lookup::Found(steve) if steve.id == 2));
+    }
+}
@@ -0,0 +1,301 @@
+// Who may be served alcohol, and a record of every drink that was.
+//
+// The check used to live inside the accept_with_note arm of the door, so a 15 year old with a
+// plain accept was never warned about. Now every visitor who is let in is checked, whatever their action.
+//
+// The legal age depends on where the treehouse is. It is set in the [serving] section of the config file,
+// either by jurisdiction or directly. legal_age wins if both are given:
+//
+//     [serving]
+//     jurisdiction = uk   # one of the codes in JURISDICTIONS, defaults to us
+//     legal_age = 18
+//
+// Drinks are written to the audit log (see audit.rs). Nothing is recorded for someone under age,
+// or whose age isn't known, the attempt is logged as refused instead:
+//
+//     [drink]
+//     time = 2026-10-18T20:15:00Z
+//     visitor = 1
+//     name = Bert
+//     drink = cider
+//
+//     [drink_refused]
+//     time = 2026-10-18T20:16:00Z
+//     visitor = 2
+//     name = Steve
+//     drink = cider
+//     reason = under 21
+//
+// Before any of that, the visitor has to be inside and still be somebody the door would let in. Nobody is
+// served on behalf of a person who went home, or who was refused or whose hours ended since they arrived.
+// Those attempts are turned down without writing anything, see NotServable.
+
+use std::fmt;
+
+use crate::clock::{Date, Timestamp};
+use crate::decision::Outcome;
+use crate::storage::{ParseError, Record};
+use crate::{Visitor, VisitorId};
+
+// Legal drinking ages by jurisdiction code. Where rules differ by region or drink, the strictest is used.
+pub const JURISDICTIONS: [(&str, u8); 8] = [
+    ("us", 21),
+    ("ca", 19),
+    ("uk", 18),
+    ("ie", 18),
+    ("au", 18),
+    ("nz", 18),
+    ("jp", 20),
+    ("kr", 19),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServingPolicy {
+    pub legal_age: u8,
+}
+
+impl Default for ServingPolicy {
+    fn default() -> Self {
+        // The treehouse has always used 21.
+        Self { legal_age: 21 }
+    }
+}
+
+impl ServingPolicy {
+    pub fn for_jurisdiction(code: &str) -> Option<Self> {
+        JURISDICTIONS
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(code))
+            .map(|&(_, legal_age)| Self { legal_age })
+    }
+
+    // Somebody whose age isn't known is not assumed to be old enough, or too young. They are
+    // refused with AgeUnknown, so staff can check their ID instead of simply turning them down.
+    pub fn may_serve(&self, visitor: &Visitor, today: Date) -> Result<(), ServingRefusal> {
+        match visitor.age_on(today) {
+            None => Err(ServingRefusal::AgeUnknown),
+            Some(age) if age < u32::from(self.legal_age) => Err(ServingRefusal::UnderAge {
+                legal_age: self.legal_age,
+            }),
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServingRefusal {
+    UnderAge { legal_age: u8 },
+    AgeUnknown,
+}
+
+impl fmt::Display for ServingRefusal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServingRefusal::UnderAge { legal_age } => write!(f, "under {}", legal_age),
+            ServingRefusal::AgeUnknown => write!(f, "age unknown"),
+        }
+    }
+}
+
+impl std::error::Error for ServingRefusal {}
+
+// Why somebody can't be served at all, whatever their age.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotServable {
+    NotInside,
+    NotAdmitted(Outcome), // what the door would decide for them now.
+}
+
+impl fmt::Display for NotServable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NotServable::NotInside => write!(f, "not inside"),
+            NotServable::NotAdmitted(Outcome::Lapsed) => write!(f, "their guest pass has lapsed"),
+            NotServable::NotAdmitted(_) => write!(f, "not admitted"),
+        }
+    }
+}
+
+impl std::error::Error for NotServable {}
+
+// Checked before Serving::attempt. inside comes from the occupancy, outcome from a fresh admission decision.
+pub fn check_servable(inside: bool, outcome: Outcome) -> Result<(), NotServable> {
+    if !inside {
+        Err(NotServable::NotInside)
+    } else if !outcome.lets_in() {
+        Err(NotServable::NotAdmitted(outcome))
+    } else {
+        Ok(())
+    }
+}
+
+// One line of the serving log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Serving {
+    pub time: Timestamp,
+    pub visitor: VisitorId,
+    pub name: String,
+    pub drink: String,
+    pub refused: Option<String>, // the reason, when the drink was not served.
+}
+
+impl Serving {
+    // Checks the policy and builds the entry for the audit log, a refusal if the visitor may not drink.
+    // today is the local date, used to work out the visitor's age.
+    pub fn attempt(
+        policy: &ServingPolicy,
+        visitor: &Visitor,
+        drink: &str,
+        time: Timestamp,
+        today: Date,
+    ) -> Self {
+        Self {
+            time,
+            visitor: visitor.id,
+            name: visitor.name.clone(),
+            drink: drink.to_string(),
+            refused: policy
+                .may_serve(visitor, today)
+                .err()
+                .map(|refusal| refusal.to_string()),
+        }
+    }
+
+    pub fn to_record(&self) -> Record {
+        let mut entry = Record::new(if self.refused.is_some() {
+            "drink_refused"
+        } else {
+            "drink"
+        });
+        entry.push("time", self.time);
+        entry.push("visitor", self.visitor);
+        entry.push("name", &self.name);
+        entry.push("drink", &self.drink);
+        if let Some(reason) = &self.refused {
+            entry.push("reason", reason);
+        }
+        entry
+    }
+
+    // Reads a [drink] or [drink_refused] entry. Returns None for every other kind of entry.
+    pub fn from_record(entry: &Record) -> Option<Result<Self, ParseError>> {
+        let refused = match entry.kind.as_str() {
+            "drink" => false,
+            "drink_refused" => true,
+            _ => return None,
+        };
+        let parse = || {
+            let error = |message: String| ParseError::new(entry.line, message);
+            let time = entry.require("time")?;
+            let time =
+                Timestamp::parse(time).ok_or_else(|| error(format!("bad time `{}`", time)))?;
+            let visitor = entry.require("visitor")?;
+            let visitor = visitor
+                .parse()
+                .map_err(|_| error(format!("bad visitor id `{}`", visitor)))?;
+            let reason = if refused {
+                Some(entry.require("reason")?.to_string())
+            } else {
+                None
+            };
+            Ok(Self {
+                time,
+                visitor,
+                name: entry.require("name")?.to_string(),
+                drink: entry.require("drink")?.to_string(),
+                refused: reason,
+            })
+        };
+        Some(parse())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VisitorAction;
+
+    fn visitor(born: Option<Date>) -> Visitor {
+        let mut visitor = Visitor::new("Steve", "Hi", VisitorAction::Accept, born);
+        visitor.id = 2;
+        visitor
+    }
+
+    fn at(time: &str) -> Timestamp {
+        Timestamp::parse(time).expect("test times are valid")
+    }
+
+    #[test]
+    fn only_admitted_visitors_inside_are_served() {
+        assert_eq!(check_servable(true, Outcome::Admitted), Ok(()));
+        assert_eq!(check_servable(true, Outcome::Probation), Ok(()));
+        assert_eq!(
+            check_servable(false, Outcome::Admitted),
+            Err(NotServable::NotInside)
+        );
+        assert_eq!(
+            check_servable(true, Outcome::Refused),
+            Err(NotServable::NotAdmitted(Outcome::Refused))
+        );
+        assert_eq!(
+            check_servable(true, Outcome::Lapsed),
+            Err(NotServable::NotAdmitted(Outcome::Lapsed))
+        );
+    }
+
+    #[test]
+    fn the_legal_age_is_reached_on_the_birthday() {
+        let policy = ServingPolicy::for_jurisdiction("UK").unwrap();
+        assert_eq!(policy.legal_age, 18);
+        let steve = visitor(Date::new(2008, 10, 18));
+        assert_eq!(
+            policy.may_serve(&steve, Date::new(2026, 10, 17).unwrap()),
+            Err(ServingRefusal::UnderAge { legal_age: 18 })
+        );
+        assert_eq!(
+            policy.may_serve(&steve, Date::new(2026, 10, 18).unwrap()),
+            Ok(())
+        );
+        assert_eq!(ServingPolicy::for_jurisdiction("xx"), None);
+    }
+
+    #[test]
+    fn unknown_ages_are_not_served() {
+        let today = Date::new(2026, 10, 18).unwrap();
+        assert_eq!(
+            ServingPolicy::default().may_serve(&visitor(None), today),
+            Err(ServingRefusal::AgeUnknown)
+        );
+    }
+
+    #[test]
+    fn attempts_read_back_from_the_log() {
+        let policy = ServingPolicy::default();
+        let today = Date::new(2026, 10, 18).unwrap();
+        let time = at("2026-10-18T20:15:00Z");
+
+        let served = Serving::attempt(
+            &policy,
+            &visitor(Date::new(1980, 1, 1)),
+            "cider",
+            time,
+            today,
+        );
+        assert_eq!(served.refused, None);
+        assert_eq!(served.to_record().kind, "drink");
+
+        let refused = Serving::attempt(
+            &policy,
+            &visitor(Date::new(2011, 1, 1)),
+            "cider",
+            time,
+            today,
+        );
+        assert_eq!(refused.refused.as_deref(), Some("under 21"));
+        let entry = refused.to_record();
+        assert_eq!(entry.kind, "drink_refused");
+        assert_eq!(Serving::from_record(&entry), Some(Ok(refused)));
+
+        assert_eq!(Serving::from_record(&Record::new("checkin")), None);
+    }
+}
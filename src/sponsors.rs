@@ -0,0 +1,287 @@
+// Sponsored guests: a member vouching for somebody new at the door.
+//
+// Somebody who isn't on the list used to be enrolled on probation with nothing to say who they were.
+// Now the door asks whether a member is vouching for them. The member is stored with the new visitor
+// as their sponsor (see storage.rs), and the vouch is written to the audit log (see audit.rs):
+//
+//     [sponsored]
+//     time = 2026-10-18T19:30:00Z
+//     sponsor = 1
+//     sponsor_name = Bert
+//     visitor = 5
+//     name = Aunt May
+//
+// The sponsor is told at the door the next time they come to it, whether they are let in, refused or
+// queued. Once the door has said it, that is written to the audit log too, so they hear it exactly once:
+//
+//     [sponsor_told]
+//     time = 2026-10-19T18:00:00Z
+//     sponsor = 1
+//     visitor = 5
+//
+// The vouches a sponsor hasn't heard about yet are the ones without such an entry.
+//
+// Not everybody can vouch. A member who is refused or on probation themselves, a guest on a pass
+// (see passes.rs) or a minor (see minors.rs) is turned down as a sponsor. With
+//
+//     [sponsors]
+//     required = true
+//
+// in the config file, somebody new is only let in when a member who can vouch does so.
+
+use std::fmt;
+
+use crate::clock::Timestamp;
+use crate::minors::MinorPolicy;
+use crate::storage::{ParseError, Record};
+use crate::{Visitor, VisitorAction, VisitorId};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SponsorSettings {
+    pub required: bool, // false lets unsponsored strangers in on probation, like before.
+}
+
+// Why a member can't vouch for anybody.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VouchRefusal {
+    Refused,
+    OnProbation,
+    Guest,
+    Minor(u8), // the policy's age, so the refusal can be explained on its own.
+}
+
+impl VouchRefusal {
+    // The message catalog key that explains the refusal, see messages.rs.
+    pub fn message_key(&self) -> &'static str {
+        match self {
+            VouchRefusal::Refused => "sponsor_refused",
+            VouchRefusal::OnProbation => "sponsor_on_probation",
+            VouchRefusal::Guest => "sponsor_is_guest",
+            VouchRefusal::Minor(_) => "sponsor_is_minor",
+        }
+    }
+}
+
+// For admin commands, the door explains refusals in the visitor's language with message_key.
+impl fmt::Display for VouchRefusal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VouchRefusal::Refused => write!(f, "they are refused"),
+            VouchRefusal::OnProbation => write!(f, "they are on probation themselves"),
+            VouchRefusal::Guest => write!(f, "they are a guest on a pass"),
+            VouchRefusal::Minor(age) => write!(f, "they are under {}", age),
+        }
+    }
+}
+
+// Whether the sponsor's own standing lets them vouch at local time.
+// A sponsor whose age isn't known can vouch, the door can't check it either way.
+pub fn can_vouch(
+    sponsor: &Visitor,
+    minors: &MinorPolicy,
+    local: Timestamp,
+) -> Result<(), VouchRefusal> {
+    match sponsor.action {
+        VisitorAction::Refuse => return Err(VouchRefusal::Refused),
+        VisitorAction::Probation => return Err(VouchRefusal::OnProbation),
+        VisitorAction::Accept | VisitorAction::AcceptWithNote { .. } => {}
+    }
+    if sponsor.pass.is_some() {
+        return Err(VouchRefusal::Guest);
+    }
+    if minors.is_minor(sponsor, local) == Some(true) {
+        return Err(VouchRefusal::Minor(minors.age));
+    }
+    Ok(())
+}
+
+// One member vouching for one new visitor. The names are kept as they were at the time,
+// so the log still reads well if either of them is renamed or removed later.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sponsorship {
+    pub time: Timestamp,
+    pub sponsor: VisitorId,
+    pub sponsor_name: String,
+    pub visitor: VisitorId,
+    pub name: String,
+    pub told: bool, // whether the sponsor has heard about it at the door.
+}
+
+impl Sponsorship {
+    pub fn new(time: Timestamp, sponsor: &Visitor, visitor: &Visitor) -> Self {
+        Self {
+            time,
+            sponsor: sponsor.id,
+            sponsor_name: sponsor.name.clone(),
+            visitor: visitor.id,
+            name: visitor.name.clone(),
+            told: false,
+        }
+    }
+
+    pub fn to_record(&self) -> Record {
+        let mut entry = Record::new("sponsored");
+        entry.push("time", self.time);
+        entry.push("sponsor", self.sponsor);
+        entry.push("sponsor_name", &self.sponsor_name);
+        entry.push("visitor", self.visitor);
+        entry.push("name", &self.name);
+        entry
+    }
+
+    // Written once the door has told the sponsor about the vouch.
+    pub fn told_entry(&self, time: Timestamp) -> Record {
+        let mut entry = Record::new("sponsor_told");
+        entry.push("time", time);
+        entry.push("sponsor", self.sponsor);
+        entry.push("visitor", self.visitor);
+        entry
+    }
+
+    // Every vouch in the audit log, oldest first, with whether the sponsor has been told about it.
+    // Entries about anything else are skipped.
+    pub fn from_entries(entries: &[Record]) -> Result<Vec<Self>, ParseError> {
+        let mut sponsorships: Vec<Self> = Vec::new();
+        for entry in entries
+            .iter()
+            .filter(|entry| ["sponsored", "sponsor_told"].contains(&entry.kind.as_str()))
+        {
+            let error = |message: String| ParseError::new(entry.line, message);
+            let id = |key: &str| {
+                let id = entry.require(key)?;
+                id.parse::<VisitorId>()
+                    .map_err(|_| error(format!("bad {} id `{}`", key, id)))
+            };
+            let time = entry.require("time")?;
+            let time =
+                Timestamp::parse(time).ok_or_else(|| error(format!("bad time `{}`", time)))?;
+            let (sponsor, visitor) = (id("sponsor")?, id("visitor")?);
+            if entry.kind == "sponsor_told" {
+                // A visitor only ever has one sponsor, so the pair finds the vouch.
+                let sponsorship = sponsorships
+                    .iter_mut()
+                    .find(|sponsorship| {
+                        sponsorship.sponsor == sponsor && sponsorship.visitor == visitor
+                    })
+                    .ok_or_else(|| {
+                        error(format!(
+                            "#{} was told about a vouch for #{} that was never made",
+                            sponsor, visitor
+                        ))
+                    })?;
+                sponsorship.told = true;
+                continue;
+            }
+            sponsorships.push(Self {
+                time,
+                sponsor,
+                sponsor_name: entry.require("sponsor_name")?.to_string(),
+                visitor,
+                name: entry.require("name")?.to_string(),
+                told: false,
+            });
+        }
+        Ok(sponsorships)
+    }
+}
+
+// The vouches the sponsor hasn't been told about yet.
+pub fn unheard(
+    sponsorships: &[Sponsorship],
+    sponsor: VisitorId,
+) -> impl Iterator<Item = &Sponsorship> {
+    sponsorships
+        .iter()
+        .filter(move |sponsorship| sponsorship.sponsor == sponsor && !sponsorship.told)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Date, UtcOffset};
+    use crate::passes::GuestPass;
+
+    fn at(time: &str) -> Timestamp {
+        Timestamp::parse(time).expect("test times are valid")
+    }
+
+    fn member(action: VisitorAction, born: Option<Date>) -> Visitor {
+        let mut visitor = Visitor::new("Bert", "Hi {name}", action, born);
+        visitor.id = 1;
+        visitor
+    }
+
+    #[test]
+    fn who_can_vouch() {
+        let minors = MinorPolicy::default();
+        let local = at("2026-10-18T19:30:00Z");
+        let born = |year| Date::new(year, 10, 18);
+
+        assert_eq!(
+            can_vouch(&member(VisitorAction::Accept, born(1980)), &minors, local),
+            Ok(())
+        );
+        let note = VisitorAction::AcceptWithNote {
+            note: "Likes cider".to_string(),
+        };
+        assert_eq!(can_vouch(&member(note, None), &minors, local), Ok(()));
+
+        let refused = member(VisitorAction::Refuse, born(1980));
+        assert_eq!(
+            can_vouch(&refused, &minors, local),
+            Err(VouchRefusal::Refused)
+        );
+        let on_probation = member(VisitorAction::Probation, born(1980));
+        assert_eq!(
+            can_vouch(&on_probation, &minors, local),
+            Err(VouchRefusal::OnProbation)
+        );
+
+        let mut guest = member(VisitorAction::Accept, born(1980));
+        let (from, until) = (born(2026).unwrap(), born(2026).unwrap());
+        guest.pass = Some(GuestPass::for_days(2, from, until, None, UtcOffset(0)));
+        assert_eq!(can_vouch(&guest, &minors, local), Err(VouchRefusal::Guest));
+
+        // Old enough on their eighteenth birthday, not the day before.
+        let minor = member(VisitorAction::Accept, Date::new(2008, 10, 19));
+        assert_eq!(
+            can_vouch(&minor, &minors, local),
+            Err(VouchRefusal::Minor(18))
+        );
+        let adult = member(VisitorAction::Accept, born(2008));
+        assert_eq!(can_vouch(&adult, &minors, local), Ok(()));
+    }
+
+    #[test]
+    fn sponsors_are_told_once() {
+        let bert = member(VisitorAction::Accept, None);
+        let mut may = Visitor::probationary("Aunt May", None);
+        may.id = 5;
+        let mut sid = Visitor::probationary("Sid", None);
+        sid.id = 6;
+
+        let for_may = Sponsorship::new(at("2026-10-18T19:30:00Z"), &bert, &may);
+        let for_sid = Sponsorship::new(at("2026-10-18T19:45:00Z"), &bert, &sid);
+        let mut entries = vec![for_may.to_record(), for_sid.to_record()];
+        let sponsorships = Sponsorship::from_entries(&entries).unwrap();
+        assert_eq!(sponsorships, [for_may.clone(), for_sid.clone()]);
+        assert_eq!(unheard(&sponsorships, 1).count(), 2);
+        assert_eq!(unheard(&sponsorships, 5).count(), 0);
+
+        entries.push(for_may.told_entry(at("2026-10-19T18:00:00Z")));
+        let sponsorships = Sponsorship::from_entries(&entries).unwrap();
+        assert!(sponsorships[0].told);
+        assert_eq!(unheard(&sponsorships, 1).collect::<Vec<_>>(), [&for_sid]);
+    }
+
+    #[test]
+    fn told_about_a_vouch_that_was_never_made() {
+        let bert = member(VisitorAction::Accept, None);
+        let may = Visitor::probationary("Aunt May", None);
+        let sponsorship = Sponsorship::new(at("2026-10-18T19:30:00Z"), &bert, &may);
+        let mut entry = sponsorship.told_entry(at("2026-10-19T18:00:00Z"));
+        entry.line = 12;
+        let error = Sponsorship::from_entries(&[entry]).unwrap_err();
+        assert_eq!(error.line, 12);
+    }
+}
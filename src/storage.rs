@@ -0,0 +1,693 @@
+// The visitor list is saved as a plain text file so it survives between runs
+// and can still be read (and repaired) by hand with any text editor.
+//
+// On-disk format, version 10:
+//
+//     # Lines starting with a hash are comments, blank lines are ignored.
+//     [treehouse]
+//     version = 10
+//     next_id = 4
+//
+//     [visitor]
+//     id = 1
+//     name = bert
+//     born = 1981-04-02
+//     action = accept
+//     greeting = Hello Bert, enjoy your treehouse.
+//
+//     [visitor]
+//     id = 2
+//     name = steve
+//     born = 2011-06-30
+//     language = es
+//     action = accept_with_note
+//     note = Lactose-free milk is in the fridge
+//     greeting = Hi Steve. Your milk is in the fridge.
+//     alias = Steven
+//     alias = Stevo
+//     group = juniors
+//     allow = weekdays 15:00-19:00
+//     guardian = 1
+//
+// Every record starts with a [section] header and is followed by `key = value` lines.
+// The value is everything after the first `=`, with surrounding spaces trimmed.
+// A backslash in a value starts an escape: \\ is a backslash, \n a new line and \r a carriage return,
+// so a value always fits on a single line.
+//
+// action is one of accept, accept_with_note, refuse or probation.
+// note is only allowed (and required) when the action is accept_with_note.
+// alias may be repeated, once for each other name the visitor answers to.
+// No alias may match another visitor's name or alias.
+// id is unique per visitor and next_id is the id the next new visitor will get.
+// Several visitors may share a name, the id is what tells them apart.
+//
+// born is the date of birth as YYYY-MM-DD, left out when it isn't known.
+// greeting is a template, so it may use placeholders like {name}, see template.rs.
+// language is the visitor's preferred language code, left out for the treehouse default (see messages.rs).
+// It was added in version 4, older files have none.
+// group, allow and deny may each be repeated and say when the visitor may come in, see access.rs.
+// They were added in version 5.
+// guardian is the id of a visitor who has to be inside before this one, if they are a minor (see minors.rs).
+// It may be repeated and was added in version 6.
+// Guests also have pass_issued_by, pass_from, pass_until and maybe pass_uses, see passes.rs.
+// They were added in version 7.
+// sponsor is the id of the member who vouched for the visitor at the door, see sponsors.rs.
+// It was added in version 8.
+// Visitors on probation have probation_since, and approved_by once for each member who approved them,
+// see probation.rs. They were added in version 9.
+//
+// Version 1 files have no ids. They are still read, and every visitor is numbered in file order.
+// Versions 1 and 2 store an `age = <years>` instead of born. An age of 0 (what newcomers used to get)
+// or below is read as unknown. Any other age becomes an estimated birth date that many years before
+// the day the file is read, which gives the right age today; `set-born` can put in the real one.
+// Greetings became templates without a new version, so files before version 10 may have greetings
+// with braces that were only ever meant as text, like `Hi Bert :-}`. Those are read as plain text,
+// with their braces escaped. From version 10 every greeting has to be a valid template.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::access::{AccessRule, Effect};
+use crate::clock::{Date, Timestamp};
+use crate::messages::{is_valid_language, normalize_language};
+use crate::passes::GuestPass;
+use crate::template;
+use crate::{Visitor, VisitorAction, VisitorId, VisitorRegistry};
+
+pub const FORMAT_VERSION: u32 = 10;
+
+// Errors are an enum so callers can tell a missing disk apart from a damaged file.
+#[derive(Debug)]
+pub enum StorageError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Corrupt {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            StorageError::Corrupt {
+                path,
+                line,
+                message,
+            } => write!(
+                f,
+                "{} is corrupt at line {}: {}",
+                path.display(),
+                line,
+                message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Io { source, .. } => Some(source),
+            StorageError::Corrupt { .. } => None,
+        }
+    }
+}
+
+// A problem found while parsing text, before we know which file it came from.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+
+    pub fn in_file(self, path: &Path) -> StorageError {
+        StorageError::Corrupt {
+            path: path.to_path_buf(),
+            line: self.line,
+            message: self.message,
+        }
+    }
+}
+
+// One [section] and the key = value lines that follow it.
+#[derive(Debug, Clone, Default)]
+pub struct Record {
+    pub kind: String,
+    pub line: usize, // line number of the [section] header, used in error messages.
+    pub fields: Vec<(String, String)>,
+}
+
+impl Record {
+    pub fn new(kind: &str) -> Self {
+        Self {
+            kind: kind.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, key: &str, value: impl ToString) {
+        self.fields.push((key.to_string(), value.to_string()));
+    }
+
+    // Returns the first value stored under key, if there is one.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    // Every value stored under key, for keys that may appear more than once.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.fields
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn require(&self, key: &str) -> Result<&str, ParseError> {
+        self.get(key).ok_or_else(|| {
+            ParseError::new(
+                self.line,
+                format!("[{}] is missing the `{}` key", self.kind, key),
+            )
+        })
+    }
+}
+
+pub fn parse_records(text: &str) -> Result<Vec<Record>, ParseError> {
+    let mut records: Vec<Record> = Vec::new();
+
+    for (index, raw) in text.lines().enumerate() {
+        let line_number = index + 1; // people count lines from 1, enumerate counts from 0.
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let kind = header
+                .strip_suffix(']')
+                .ok_or_else(|| ParseError::new(line_number, "section header is missing `]`"))?;
+            records.push(Record {
+                kind: kind.trim().to_string(),
+                line: line_number,
+                fields: Vec::new(),
+            });
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| ParseError::new(line_number, "expected `key = value`"))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(ParseError::new(line_number, "key is empty"));
+        }
+        let value =
+            unescape(value.trim()).map_err(|message| ParseError::new(line_number, message))?;
+
+        // last_mut gives a mutable reference to the record we are currently filling in.
+        match records.last_mut() {
+            Some(record) => record.fields.push((key.to_string(), value)),
+            None => {
+                return Err(ParseError::new(
+                    line_number,
+                    "value found before any [section] header",
+                ))
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+pub fn write_records(records: &[Record]) -> String {
+    let mut text = String::new();
+    for record in records {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&format!("[{}]\n", record.kind));
+        for (key, value) in &record.fields {
+            text.push_str(&format!("{} = {}\n", key, escape(value)));
+        }
+    }
+    text
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
+
+fn unescape(value: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => return Err(format!("unknown escape `\\{}`", other)),
+            None => return Err("value ends with a lone `\\`".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+// Writes the text to a temporary file next to path and then renames it over path.
+// A rename within one directory is atomic, so a crash leaves either the old file or the new one,
+// never a half written mixture of both.
+pub fn write_atomically(path: &Path, text: &str) -> Result<(), StorageError> {
+    let io_error = |source| StorageError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    if let Some(dir) = path.parent() {
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+    }
+
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    let mut file = fs::File::create(&tmp_path).map_err(io_error)?;
+    file.write_all(text.as_bytes()).map_err(io_error)?;
+    file.sync_all().map_err(io_error)?; // make sure the bytes are on disk before the rename.
+    fs::rename(&tmp_path, path).map_err(io_error)
+}
+
+fn action_from_record(record: &Record) -> Result<VisitorAction, ParseError> {
+    VisitorAction::from_label(record.require("action")?, record.get("note"))
+        .map_err(|message| ParseError::new(record.line, message))
+}
+
+fn number_from_record<T: std::str::FromStr>(record: &Record, key: &str) -> Result<T, ParseError> {
+    let text = record.require(key)?;
+    text.parse::<T>().map_err(|_| {
+        ParseError::new(
+            record.line,
+            format!("{} `{}` is not a valid number", key, text),
+        )
+    })
+}
+
+// The largest id is never handed out, so the id after a visitor's always fits, see VisitorRegistry::add.
+// next_id may be the largest id, which means there are none left.
+fn id_from_record(record: &Record, key: &str) -> Result<VisitorId, ParseError> {
+    let id: VisitorId = number_from_record(record, key)?;
+    if id == VisitorId::MAX {
+        return Err(ParseError::new(
+            record.line,
+            format!("{} {} is too large", key, id),
+        ));
+    }
+    Ok(id)
+}
+
+// Turns a version 1 or 2 age into a birth date, see the note at the top of the file.
+fn estimated_birth_date(age: i8, today: Date) -> Option<Date> {
+    if age <= 0 {
+        return None;
+    }
+    let year = today.year - i32::from(age);
+    // Someone "born" on 29 February in a year that doesn't have one gets the 28th instead.
+    Date::new(year, today.month, today.day).or_else(|| Date::new(year, today.month, 28))
+}
+
+fn visitor_from_record(record: &Record, version: u32, today: Date) -> Result<Visitor, ParseError> {
+    let birth_date = if version >= 3 {
+        match record.get("born") {
+            Some(born) => Some(Date::parse(born).ok_or_else(|| {
+                ParseError::new(
+                    record.line,
+                    format!("born `{}` is not a date like 2011-06-30", born),
+                )
+            })?),
+            None => None,
+        }
+    } else {
+        let age = record.require("age")?;
+        let age = age.parse::<i8>().map_err(|_| {
+            ParseError::new(
+                record.line,
+                format!("age `{}` is not a number from -128 to 127", age),
+            )
+        })?;
+        estimated_birth_date(age, today)
+    };
+
+    let greeting = record.require("greeting")?;
+    let greeting = match template::validate(greeting) {
+        Ok(()) => greeting.to_string(),
+        Err(_) if version < 10 => template::escape(greeting),
+        Err(error) => {
+            return Err(ParseError::new(
+                record.line,
+                format!("greeting `{}`: {}", greeting, error),
+            ))
+        }
+    };
+
+    let mut visitor = Visitor::new(
+        record.require("name")?,
+        &greeting,
+        action_from_record(record)?,
+        birth_date,
+    );
+    if version >= 4 {
+        if let Some(language) = record.get("language") {
+            if !is_valid_language(language) {
+                return Err(ParseError::new(
+                    record.line,
+                    format!(
+                        "language `{}` is not a language code like en or es",
+                        language
+                    ),
+                ));
+            }
+            visitor.language = Some(normalize_language(language));
+        }
+    }
+    if version >= 5 {
+        visitor.groups = record.get_all("group").map(str::to_string).collect();
+        for effect in [Effect::Allow, Effect::Deny] {
+            for schedule in record.get_all(effect.label()) {
+                visitor.access.push(
+                    AccessRule::parse(effect, schedule)
+                        .map_err(|message| ParseError::new(record.line, message))?,
+                );
+            }
+        }
+    }
+    if version >= 6 {
+        for guardian in record.get_all("guardian") {
+            let guardian = guardian.parse().map_err(|_| {
+                ParseError::new(record.line, format!("bad guardian id `{}`", guardian))
+            })?;
+            visitor.guardians.push(guardian);
+        }
+    }
+    if version >= 9 {
+        if let Some(since) = record.get("probation_since") {
+            visitor.probation_since = Some(Timestamp::parse(since).ok_or_else(|| {
+                ParseError::new(
+                    record.line,
+                    format!("probation_since `{}` is not a time", since),
+                )
+            })?);
+        }
+        for member in record.get_all("approved_by") {
+            let member = member.parse().map_err(|_| {
+                ParseError::new(record.line, format!("bad approved_by id `{}`", member))
+            })?;
+            visitor.approved_by.push(member);
+        }
+    }
+    if version >= 8 && record.get("sponsor").is_some() {
+        visitor.sponsor = Some(number_from_record(record, "sponsor")?);
+    }
+    if version >= 7 && record.get("pass_issued_by").is_some() {
+        let time = |key: &str| {
+            let text = record.require(key)?;
+            Timestamp::parse(text).ok_or_else(|| {
+                ParseError::new(record.line, format!("{} `{}` is not a time", key, text))
+            })
+        };
+        visitor.pass = Some(GuestPass {
+            issued_by: number_from_record(record, "pass_issued_by")?,
+            valid_from: time("pass_from")?,
+            valid_until: time("pass_until")?,
+            max_uses: match record.get("pass_uses") {
+                Some(_) => Some(number_from_record(record, "pass_uses")?),
+                None => None,
+            },
+        });
+    }
+    if version >= 2 {
+        visitor.id = id_from_record(record, "id")?;
+        if visitor.id == 0 {
+            return Err(ParseError::new(record.line, "id 0 is not allowed"));
+        }
+    }
+    Ok(visitor)
+}
+
+fn visitor_to_record(visitor: &Visitor) -> Record {
+    let mut record = Record::new("visitor");
+    record.push("id", visitor.id);
+    record.push("name", &visitor.name);
+    if let Some(born) = visitor.birth_date {
+        record.push("born", born);
+    }
+    if let Some(language) = &visitor.language {
+        record.push("language", language);
+    }
+    record.push("action", visitor.action.label());
+    if let VisitorAction::AcceptWithNote { note } = &visitor.action {
+        record.push("note", note);
+    }
+    record.push("greeting", &visitor.greeting);
+    for alias in &visitor.aliases {
+        record.push("alias", alias);
+    }
+    for group in &visitor.groups {
+        record.push("group", group);
+    }
+    for rule in &visitor.access {
+        record.push(rule.effect.label(), &rule.schedule);
+    }
+    for guardian in &visitor.guardians {
+        record.push("guardian", guardian);
+    }
+    if let Some(sponsor) = visitor.sponsor {
+        record.push("sponsor", sponsor);
+    }
+    if let Some(since) = visitor.probation_since {
+        record.push("probation_since", since);
+    }
+    for member in &visitor.approved_by {
+        record.push("approved_by", member);
+    }
+    if let Some(pass) = &visitor.pass {
+        record.push("pass_issued_by", pass.issued_by);
+        record.push("pass_from", pass.valid_from);
+        record.push("pass_until", pass.valid_until);
+        if let Some(uses) = pass.max_uses {
+            record.push("pass_uses", uses);
+        }
+    }
+    record
+}
+
+// today is only used to turn the ages in old files into birth dates.
+pub fn parse_registry(text: &str, today: Date) -> Result<VisitorRegistry, ParseError> {
+    let records = parse_records(text)?;
+
+    // The [treehouse] header has to come first, because it says how to read everything after it.
+    let Some(header) = records.first().filter(|record| record.kind == "treehouse") else {
+        return Err(ParseError::new(1, "missing [treehouse] version header"));
+    };
+    let version: u32 = number_from_record(header, "version")?;
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(ParseError::new(
+            header.line,
+            format!("unsupported format version `{}`", version),
+        ));
+    }
+
+    let mut registry = VisitorRegistry::new();
+    for record in &records[1..] {
+        match record.kind.as_str() {
+            "visitor" => {
+                let visitor = visitor_from_record(record, version, today)?;
+                if visitor.id != 0 && registry.get(visitor.id).is_some() {
+                    return Err(ParseError::new(
+                        record.line,
+                        format!("id {} is used by more than one visitor", visitor.id),
+                    ));
+                }
+                let id = registry.restore(visitor);
+                for alias in record.get_all("alias") {
+                    registry
+                        .add_alias(id, alias)
+                        .map_err(|error| ParseError::new(record.line, error.to_string()))?;
+                }
+            }
+            other => {
+                return Err(ParseError::new(
+                    record.line,
+                    format!("unknown section [{}]", other),
+                ))
+            }
+        }
+    }
+
+    if version >= 2 {
+        registry.set_next_id(number_from_record(header, "next_id")?);
+    }
+    Ok(registry)
+}
+
+pub fn format_registry(registry: &VisitorRegistry) -> String {
+    let mut header = Record::new("treehouse");
+    header.push("version", FORMAT_VERSION);
+    header.push("next_id", registry.next_id());
+
+    let mut records = vec![header];
+    records.extend(registry.iter().map(visitor_to_record));
+
+    let mut text =
+        String::from("# rust-treehouse visitor list, see src/storage.rs for the format.\n");
+    text.push_str(&write_records(&records));
+    text
+}
+
+// Returns Ok(None) when there is no file yet, so the caller can decide what a fresh start looks like.
+// today is passed on to parse_registry.
+pub fn load_registry(path: &Path, today: Date) -> Result<Option<VisitorRegistry>, StorageError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => {
+            return Err(StorageError::Io {
+                path: path.to_path_buf(),
+                source,
+            })
+        }
+    };
+    parse_registry(&text, today)
+        .map(Some)
+        .map_err(|error| error.in_file(path))
+}
+
+pub fn save_registry(path: &Path, registry: &VisitorRegistry) -> Result<(), StorageError> {
+    write_atomically(path, &format_registry(registry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> VisitorRegistry {
+        let mut registry = VisitorRegistry::new();
+        let mut bert = Visitor::new(
+            "Bert",
+            "Hi {name}, visit {visits}",
+            VisitorAction::AcceptWithNote {
+                note: "Two lines\nof note".to_string(),
+            },
+            Date::new(1980, 5, 17),
+        );
+        bert.aliases.push("Bertie".to_string());
+        bert.language = Some("es".to_string());
+        let bert = registry.add(bert).unwrap();
+        let steve = registry.add(Visitor::probationary("Steve", None)).unwrap();
+        registry.get_mut(steve).unwrap().guardians.push(bert);
+        registry
+    }
+
+    fn today() -> Date {
+        Date::new(2026, 10, 18).unwrap()
+    }
+
+    // The start of a file in the current format, up to the first blank line.
+    fn header(next_id: u32) -> String {
+        format!(
+            "[treehouse]\nversion = {}\nnext_id = {}\n",
+            FORMAT_VERSION, next_id
+        )
+    }
+
+    #[test]
+    fn a_saved_list_reads_back_the_same() {
+        let registry = registry();
+        let text = format_registry(&registry);
+        let read = parse_registry(&text, today()).unwrap();
+        assert_eq!(read.next_id(), registry.next_id());
+        assert_eq!(read.len(), registry.len());
+        for (before, after) in registry.iter().zip(read.iter()) {
+            assert_eq!(before.id, after.id);
+            assert_eq!(before.name, after.name);
+            assert_eq!(before.aliases, after.aliases);
+            assert_eq!(before.action, after.action);
+            assert_eq!(before.birth_date, after.birth_date);
+            assert_eq!(before.greeting, after.greeting);
+            assert_eq!(before.language, after.language);
+            assert_eq!(before.guardians, after.guardians);
+        }
+        assert_eq!(format_registry(&read), text);
+    }
+
+    #[test]
+    fn damaged_files_say_where() {
+        let error = |text: &str| parse_registry(text, today()).unwrap_err();
+        assert_eq!(error("[visitor]\nid = 1\n").line, 1);
+        assert_eq!(error("[treehouse]\nversion = 99\n").line, 1);
+        assert_eq!(error(&format!("{}\nno equals", header(1))).line, 5);
+        assert_eq!(error(&format!("{}bad = \\q", header(2))).line, 4);
+
+        let missing_name = format!(
+            "{}\n[visitor]\nid = 1\naction = accept\ngreeting = Hi\n",
+            header(2)
+        );
+        let missing_name = error(&missing_name);
+        assert_eq!(missing_name.line, 5);
+        assert!(missing_name.message.contains("`name`"));
+
+        let twice = format!(
+            "{}\n[visitor]\nid = 1\nname = A\naction = accept\ngreeting = Hi\n\n[visitor]\nid = 1\nname = B\naction = accept\ngreeting = Hi\n",
+            header(3)
+        );
+        assert_eq!(error(&twice).line, 11);
+    }
+
+    #[test]
+    fn greetings_from_older_files_are_read_as_text() {
+        let text = "[treehouse]\nversion = 9\nnext_id = 2\n\n[visitor]\nid = 1\nname = Bert\naction = accept\ngreeting = Hi Bert :-}\n";
+        let registry = parse_registry(text, today()).unwrap();
+        assert_eq!(registry.get(1).unwrap().greeting, "Hi Bert :-}}");
+
+        let current = text.replace("version = 9", &format!("version = {}", FORMAT_VERSION));
+        assert_eq!(parse_registry(&current, today()).unwrap_err().line, 5);
+    }
+    #[test]
+    fn ages_from_version_two_become_birth_dates() {
+        let text = "[treehouse]\nversion = 2\nnext_id = 3\n\n[visitor]\nid = 1\nname = Bert\nage = 45\naction = accept\ngreeting = Hi\n\n[visitor]\nid = 2\nname = May\nage = 0\naction = probation\ngreeting = New friend\n";
+        let registry = parse_registry(text, today()).unwrap();
+        let bert = registry.get(1).unwrap();
+        assert_eq!(bert.birth_date, Date::new(1981, 10, 18));
+        assert_eq!(bert.age_on(today()), Some(45));
+        assert_eq!(registry.get(2).unwrap().birth_date, None);
+
+        // Read on 29 February, the estimate falls on the 28th in years without one.
+        let leap_day = Date::new(2028, 2, 29).unwrap();
+        let registry = parse_registry(text, leap_day).unwrap();
+        assert_eq!(registry.get(1).unwrap().birth_date, Date::new(1983, 2, 28));
+    }
+}
@@ -0,0 +1,250 @@
+// A very small template language for greetings, so "Hello Bert, enjoy your treehouse." can become
+// "Hello {name}, this is visit number {visits}." and stay right after a rename.
+//
+// Placeholders are written in braces:
+//
+//     {name}        the visitor's name
+//     {visits}      which visit this is, counting this one, so 1 on the first visit
+//     {last_visit}  the date they were last let in, or "never" in their language
+//     {note}        the note of an accept_with_note visitor, empty for everybody else
+//
+// {{ and }} stand for a literal { and }. Anything else in braces is an error, and templates are
+// checked when a visitor is saved or the config is read, so a typo is caught long before the door.
+
+use std::fmt;
+
+use crate::clock::Date;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placeholder {
+    Name,
+    Visits,
+    LastVisit,
+    Note,
+}
+
+impl Placeholder {
+    pub const ALL: [Placeholder; 4] = [
+        Placeholder::Name,
+        Placeholder::Visits,
+        Placeholder::LastVisit,
+        Placeholder::Note,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Placeholder::Name => "name",
+            Placeholder::Visits => "visits",
+            Placeholder::LastVisit => "last_visit",
+            Placeholder::Note => "note",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Value(Placeholder),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+// position counts characters from 1, so it matches what people see when they read the template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    UnknownPlaceholder { name: String, position: usize },
+    Unclosed { position: usize },
+    StrayBrace { position: usize },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateError::UnknownPlaceholder { name, position } => {
+                let known: Vec<String> = Placeholder::ALL
+                    .iter()
+                    .map(|placeholder| format!("{{{}}}", placeholder.label()))
+                    .collect();
+                write!(
+                    f,
+                    "unknown placeholder {{{}}} at character {}, expected one of {}",
+                    name,
+                    position,
+                    known.join(", ")
+                )
+            }
+            TemplateError::Unclosed { position } => write!(
+                f,
+                "the {{ at character {} is never closed, use {{{{ for a brace",
+                position
+            ),
+            TemplateError::StrayBrace { position } => write!(
+                f,
+                "the }} at character {} has no {{ before it, use }}}} for a brace",
+                position
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+// What the placeholders are filled in with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateValues<'a> {
+    pub name: &'a str,
+    pub visits: usize,
+    pub last_visit: Option<Date>,
+    pub never: &'a str, // what {last_visit} says when there wasn't one, from the message catalog.
+    pub note: Option<&'a str>,
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self, TemplateError> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        // peekable lets us look at the next character without using it up, to spot {{ and }}.
+        let mut chars = text.chars().enumerate().peekable();
+
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|&(_, next)| next == '{').is_some() => literal.push('{'),
+                '}' if chars.next_if(|&(_, next)| next == '}').is_some() => literal.push('}'),
+                '}' => {
+                    return Err(TemplateError::StrayBrace {
+                        position: index + 1,
+                    })
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '}')) => break,
+                            Some((_, c)) => name.push(c),
+                            None => {
+                                return Err(TemplateError::Unclosed {
+                                    position: index + 1,
+                                })
+                            }
+                        }
+                    }
+                    let placeholder = Placeholder::ALL
+                        .into_iter()
+                        .find(|placeholder| placeholder.label() == name.trim())
+                        .ok_or(TemplateError::UnknownPlaceholder {
+                            name,
+                            position: index + 1,
+                        })?;
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Value(placeholder));
+                }
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Text(literal));
+        }
+        Ok(Self { pieces })
+    }
+
+    pub fn render(&self, values: &TemplateValues) -> String {
+        let mut text = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(literal) => text.push_str(literal),
+                Piece::Value(Placeholder::Name) => text.push_str(values.name),
+                Piece::Value(Placeholder::Visits) => text.push_str(&values.visits.to_string()),
+                Piece::Value(Placeholder::LastVisit) => match values.last_visit {
+                    Some(date) => text.push_str(&date.to_string()),
+                    None => text.push_str(values.never),
+                },
+                Piece::Value(Placeholder::Note) => text.push_str(values.note.unwrap_or("")),
+            }
+        }
+        text
+    }
+}
+
+// Turns plain text into a template that renders as that text, by doubling every brace.
+pub fn escape(text: &str) -> String {
+    text.replace('{', "{{").replace('}', "}}")
+}
+
+// Checks a template without keeping it, for places that only store the text.
+pub fn validate(text: &str) -> Result<(), TemplateError> {
+    Template::parse(text).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(note: Option<&str>) -> TemplateValues<'_> {
+        TemplateValues {
+            name: "Bert",
+            visits: 3,
+            last_visit: Date::new(2026, 10, 11),
+            never: "never",
+            note,
+        }
+    }
+
+    fn render(text: &str) -> String {
+        Template::parse(text).unwrap().render(&values(None))
+    }
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        assert_eq!(
+            render("Hello {name}, visit {visits}, last here {last_visit}."),
+            "Hello Bert, visit 3, last here 2026-10-11."
+        );
+        assert_eq!(render("{ name }{note}!"), "Bert!");
+        let first_visit = TemplateValues {
+            last_visit: None,
+            ..values(Some("Likes cider"))
+        };
+        let template = Template::parse("{last_visit}: {note}").unwrap();
+        assert_eq!(template.render(&first_visit), "never: Likes cider");
+    }
+
+    #[test]
+    fn parse_errors_say_where() {
+        assert_eq!(
+            Template::parse("Hi {name"),
+            Err(TemplateError::Unclosed { position: 4 })
+        );
+        assert_eq!(
+            Template::parse("Hi {nmae}"),
+            Err(TemplateError::UnknownPlaceholder {
+                name: "nmae".to_string(),
+                position: 4
+            })
+        );
+        assert_eq!(
+            Template::parse("Hi name}"),
+            Err(TemplateError::StrayBrace { position: 8 })
+        );
+        // Positions count characters, not bytes.
+        assert_eq!(
+            Template::parse("¡Olé {name}}"),
+            Err(TemplateError::StrayBrace { position: 12 })
+        );
+        let error = validate("{visit}").unwrap_err().to_string();
+        assert!(error.contains("{name}, {visits}, {last_visit}, {note}"));
+    }
+
+    #[test]
+    fn braces_are_escaped() {
+        assert_eq!(render("{{name}} is {name}"), "{name} is Bert");
+        assert_eq!(escape("Hi {Bert} }{"), "Hi {{Bert}} }}{{");
+        for text in ["Hi {Bert}", "}{", "{{", "plain"] {
+            assert_eq!(render(&escape(text)), text);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+// A record of everybody who came to the door and what happened to them.
+//
+// Visits are kept in the audit log (see audit.rs), one [visit] entry per arrival:
+//
+//     [visit]
+//     time = 2026-10-18T14:05:00Z
+//     entered = stevo
+//     visitor = 2
+//     action = accept_with_note
+//     note = Lactose-free milk is in the fridge
+//     enrolled = false
+//
+// visitor, action and note are left out when the name didn't lead to anybody on the list.
+// enrolled is true when this arrival added the visitor to the list as a probationary member.
+
+use std::path::Path;
+
+use crate::audit::AuditLog;
+use crate::clock::Timestamp;
+use crate::storage::{ParseError, Record, StorageError};
+use crate::{VisitorAction, VisitorId};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisitRecord {
+    pub time: Timestamp,
+    pub entered_name: String,
+    pub visitor: Option<VisitorId>,
+    pub action: Option<VisitorAction>,
+    pub enrolled: bool,
+}
+
+// Which visits to return from a query. None means "don't filter on this".
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VisitFilter {
+    pub visitor: Option<VisitorId>,
+    pub from: Option<Timestamp>,  // inclusive
+    pub until: Option<Timestamp>, // exclusive
+}
+
+impl VisitFilter {
+    pub fn matches(&self, visit: &VisitRecord) -> bool {
+        self.includes(visit.visitor, visit.time)
+    }
+
+    // The same test for anything else that happened to a visitor at a time, e.g. an occupancy::Stay.
+    pub fn includes(&self, visitor: Option<VisitorId>, time: Timestamp) -> bool {
+        // is_none_or is true for None, and otherwise asks the closure.
+        self.visitor.is_none_or(|id| visitor == Some(id))
+            && self.from.is_none_or(|from| time >= from)
+            && self.until.is_none_or(|until| time < until)
+    }
+}
+
+impl VisitRecord {
+    pub fn to_record(&self) -> Record {
+        let mut record = Record::new("visit");
+        record.push("time", self.time);
+        record.push("entered", &self.entered_name);
+        if let Some(id) = self.visitor {
+            record.push("visitor", id);
+        }
+        if let Some(action) = &self.action {
+            record.push("action", action.label());
+            if let VisitorAction::AcceptWithNote { note } = action {
+                record.push("note", note);
+            }
+        }
+        record.push("enrolled", self.enrolled);
+        record
+    }
+
+    pub fn from_record(record: &Record) -> Result<Self, ParseError> {
+        let error = |message: String| ParseError::new(record.line, message);
+        if record.kind != "visit" {
+            return Err(error(format!("unknown section [{}]", record.kind)));
+        }
+
+        let time = record.require("time")?;
+        let time = Timestamp::parse(time).ok_or_else(|| error(format!("bad time `{}`", time)))?;
+        let visitor = match record.get("visitor") {
+            Some(id) => Some(
+                id.parse()
+                    .map_err(|_| error(format!("bad visitor id `{}`", id)))?,
+            ),
+            None => None,
+        };
+        let action = match record.get("action") {
+            Some(label) => {
+                Some(VisitorAction::from_label(label, record.get("note")).map_err(error)?)
+            }
+            None => None,
+        };
+        let enrolled = match record.require("enrolled")? {
+            "true" => true,
+            "false" => false,
+            other => {
+                return Err(error(format!(
+                    "enrolled must be true or false, not `{}`",
+                    other
+                )))
+            }
+        };
+
+        Ok(Self {
+            time,
+            entered_name: record.require("entered")?.to_string(),
+            visitor,
+            action,
+            enrolled,
+        })
+    }
+}
+
+// The visits in the audit log. Admin changes are kept in the same file but are skipped here.
+#[derive(Debug, Clone)]
+pub struct VisitLog {
+    audit: AuditLog,
+}
+
+impl VisitLog {
+    pub fn new(audit: AuditLog) -> Self {
+        Self { audit }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.audit.path()
+    }
+
+    pub fn append(&self, visit: &VisitRecord) -> Result<(), StorageError> {
+        self.audit.append(&visit.to_record())
+    }
+
+    // Every visit in the order they happened. A log that doesn't exist yet is empty.
+    pub fn read_all(&self) -> Result<Vec<VisitRecord>, StorageError> {
+        self.audit
+            .entries()?
+            .iter()
+            .filter(|entry| entry.kind == "visit")
+            .map(VisitRecord::from_record)
+            .collect::<Result<_, _>>() // collecting Results stops at the first error.
+            .map_err(|error| error.in_file(self.path()))
+    }
+
+    pub fn query(&self, filter: &VisitFilter) -> Result<Vec<VisitRecord>, StorageError> {
+        let mut visits = self.read_all()?;
+        // retain keeps only the elements the closure returns true for.
+        visits.retain(|visit| filter.matches(visit));
+        Ok(visits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::admin_entry;
+
+    fn at(time: &str) -> Timestamp {
+        Timestamp::parse(time).expect("test times are valid")
+    }
+
+    fn visit(time: &str, visitor: Option<VisitorId>) -> VisitRecord {
+        VisitRecord {
+            time: at(time),
+            entered_name: "stevo".to_string(),
+            visitor,
+            action: visitor.map(|_| VisitorAction::AcceptWithNote {
+                note: "Lactose-free milk is in the fridge".to_string(),
+            }),
+            enrolled: false,
+        }
+    }
+
+    #[test]
+    fn visits_read_back_the_same() {
+        for visit in [
+            visit("2026-10-18T14:05:00Z", Some(2)),
+            visit("2026-10-18T14:06:00Z", None),
+        ] {
+            assert_eq!(VisitRecord::from_record(&visit.to_record()), Ok(visit));
+        }
+    }
+
+    #[test]
+    fn bad_visits_are_rejected() {
+        let good = visit("2026-10-18T14:05:00Z", Some(2)).to_record();
+        let with = |key: &str, value: &str| {
+            let mut record = good.clone();
+            for field in record.fields.iter_mut().filter(|(name, _)| name == key) {
+                field.1 = value.to_string();
+            }
+            VisitRecord::from_record(&record)
+        };
+        assert!(with("time", "yesterday")
+            .unwrap_err()
+            .message
+            .contains("yesterday"));
+        assert!(with("visitor", "two").is_err());
+        assert!(with("action", "maybe").is_err());
+        assert!(with("enrolled", "yes")
+            .unwrap_err()
+            .message
+            .contains("true or false"));
+        assert!(VisitRecord::from_record(&Record::new("checkin")).is_err());
+    }
+
+    #[test]
+    fn filters_match_visitor_and_time() {
+        let visits = [
+            visit("2026-10-17T23:59:59Z", Some(2)),
+            visit("2026-10-18T00:00:00Z", Some(2)),
+            visit("2026-10-18T12:00:00Z", Some(3)),
+            visit("2026-10-18T13:00:00Z", None),
+            visit("2026-10-19T00:00:00Z", Some(2)),
+        ];
+        let matching = |filter: VisitFilter| -> Vec<usize> {
+            (0..visits.len())
+                .filter(|&index| filter.matches(&visits[index]))
+                .collect()
+        };
+
+        assert_eq!(matching(VisitFilter::default()), [0, 1, 2, 3, 4]);
+        assert_eq!(
+            matching(VisitFilter {
+                visitor: Some(2),
+                ..VisitFilter::default()
+            }),
+            [0, 1, 4]
+        );
+        // from is included and until is not, so one day runs from midnight to midnight.
+        let day = VisitFilter {
+            from: Some(at("2026-10-18T00:00:00Z")),
+            until: Some(at("2026-10-19T00:00:00Z")),
+            ..VisitFilter::default()
+        };
+        assert_eq!(matching(day), [1, 2, 3]);
+        assert_eq!(
+            matching(VisitFilter {
+                visitor: Some(2),
+                ..day
+            }),
+            [1]
+        );
+    }
+
+    #[test]
+    fn queries_skip_everything_but_visits() {
+        let path =
+            std::env::temp_dir().join(format!("treehouse-visits-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let log = VisitLog::new(AuditLog::new(&path));
+        assert!(log.read_all().unwrap().is_empty());
+
+        log.append(&visit("2026-10-18T14:05:00Z", Some(2))).unwrap();
+        log.audit
+            .append(&admin_entry(at("2026-10-18T14:06:00Z"), "remove", &[]))
+            .unwrap();
+        log.append(&visit("2026-10-18T14:07:00Z", None)).unwrap();
+
+        assert_eq!(log.read_all().unwrap().len(), 2);
+        let filter = VisitFilter {
+            visitor: Some(2),
+            ..VisitFilter::default()
+        };
+        assert_eq!(
+            log.query(&filter).unwrap(),
+            [visit("2026-10-18T14:05:00Z", Some(2))]
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::access::{self, AccessRule};
+use crate::clock::{Date, Timestamp};
+use crate::decision::{AdmissionContext, AdmissionDecision, Outcome, Warning};
+use crate::minors::Enforcement;
+use crate::names::{display_name, name_key, NameMatching};
+use crate::passes::{GuestPass, PassStatus};
+use crate::serving::ServingRefusal;
+use crate::sponsors;
+use crate::template::TemplateValues;
+
+// Structs are declared with pub so that code outside this module (and outside the crate) can use them.
+// Fields are private by default too, so each one that other tools need to read is also marked pub.
+
+// A type alias gives an existing type a second name. It documents what the number means.
+pub type VisitorId = u32;
+
+// The debug placeholders {:?} for raw printing, and {:#?} for pretty printing
+// can be used on any type that supports the Debug trait.
+// The Debug trait is added with a derive attribute.
+// Deriving requires that every member field in the structure supports the feature being derived.
+
+#[derive(Debug, Clone)]
+pub struct Visitor {
+    pub id: VisitorId, // 0 until the registry hands out a real id, see VisitorRegistry::add.
+    pub name: String,  // the display name, with its original capitalisation. See names.rs.
+    pub aliases: Vec<String>, // other names that find this visitor, e.g. "Stevo". See VisitorRegistry::add_alias.
+    pub action: VisitorAction,
+    // The age used to be an i8, which could be negative and went stale every birthday.
+    // Now the age is worked out from the birth date whenever it is needed, see age_on.
+    // None means we don't know it, which is not the same as being 0 years old.
+    pub birth_date: Option<Date>,
+    pub greeting: String,
+    // The language they'd like to be spoken to in, e.g. "es". None means the treehouse default, see messages.rs.
+    pub language: Option<String>,
+    // When they may come in, on top of their action. See access.rs.
+    pub groups: Vec<String>,
+    pub access: Vec<AccessRule>,
+    // Visitors who have to be inside before this one is let in, if they are a minor. See minors.rs.
+    pub guardians: Vec<VisitorId>,
+    // Guests are only let in while their pass is good, see passes.rs. None for everybody else.
+    pub pass: Option<GuestPass>,
+    // The member who vouched for them when they first came to the door, see sponsors.rs.
+    pub sponsor: Option<VisitorId>,
+    // When their probation started and which members approved them, see probation.rs.
+    pub probation_since: Option<Timestamp>,
+    pub approved_by: Vec<VisitorId>,
+}
+
+impl Visitor {
+    // impl implements functions for a struct, it is followed the name of the struct to implement.
+    // methods can access the struct contents. Associated functions, can't.
+
+    // new is an associated function that is a constructor as it returns Self.
+    pub fn new(
+        name: &str,
+        greeting: &str,
+        action: VisitorAction,
+        birth_date: Option<Date>,
+    ) -> Self {
+        // Self (with capital) refers to struct type.
+        // Note that not initialising all fields in a struct results in a compilation error
+        Self {
+            id: 0,
+            name: display_name(name), // display_name tidies the spacing but keeps the capitals, "Bert" stays "Bert".
+            greeting: greeting.to_string(),
+            aliases: Vec::new(), // Vec::new() creates an empty vector, aliases are added later.
+            // if the data is in a variable with the same name as the structs field name
+            action, // the colon and value can be omitted. Rust will just use the variable of the same name.
+            birth_date,
+            language: None,
+            groups: Vec::new(),
+            access: Vec::new(),
+            guardians: Vec::new(),
+            pass: None,
+            sponsor: None,
+            probation_since: None,
+            approved_by: Vec::new(),
+        } // lack of semi-colon here is an implicit return.
+    }
+
+    // Someone who isn't on the list yet. They are let in on probation with a generic greeting.
+    // The birth date is whatever they told us at the door, if anything.
+    pub fn probationary(name: &str, birth_date: Option<Date>) -> Self {
+        Self::new(name, "New friend", VisitorAction::Probation, birth_date)
+    }
+
+    // Age in whole years on the given day, or None if the birth date isn't known.
+    pub fn age_on(&self, today: Date) -> Option<u32> {
+        let born = self.birth_date?;
+        // The birthday hasn't come yet this year if today is earlier in the calendar than it.
+        let before_birthday = (today.month, today.day) < (born.month, born.day);
+        let years = today.year - born.year - i32::from(before_birthday);
+        // A birth date in the future is a typo, not a negative age.
+        u32::try_from(years).ok()
+    }
+
+    // The normalized form of the name that lookups compare against.
+    pub fn key(&self, matching: NameMatching) -> String {
+        name_key(&self.name, matching)
+    }
+
+    // The keys of the name and of every alias, all of which find this visitor.
+    // chain joins two iterators end to end, so the name comes first and the aliases follow.
+    pub fn keys(&self, matching: NameMatching) -> impl Iterator<Item = String> + '_ {
+        std::iter::once(&self.name)
+            .chain(self.aliases.iter())
+            .map(move |name| name_key(name, matching))
+    }
+
+    // Works out what should happen at the door without printing anything.
+    // Front ends decide how to show the decision, see decision::TerminalRenderer for the original output.
+    // The serving policy is checked for everybody who is let in, see serving.rs,
+    // and the greeting can change with the day and the hour, see greeting.rs.
+    // Visitors with access rules are only let in when their rules allow it, see access.rs,
+    // and minors have to meet the curfew and guardian rules, see minors.rs.
+    // A guest whose pass has lapsed gets Outcome::Lapsed instead of being refused, see passes.rs.
+    // Sponsors hear about anybody they vouched for that they haven't been told about yet, see sponsors.rs.
+    pub fn admission_decision(&self, context: &AdmissionContext) -> AdmissionDecision {
+        // &self as a parameter means the method has access to the struct contents.
+        // self (lowercase) refers to the instance of the struct, not its type.
+        let (visits, last_visit) = context.occupancy.history(self.id);
+        let note = match &self.action {
+            VisitorAction::AcceptWithNote { note } => Some(note.as_str()),
+            _ => None,
+        };
+        let language = context.messages.language_for(self.language.as_deref());
+        let never = context.messages.text(language, "never", &[]);
+        let values = TemplateValues {
+            name: &self.name,
+            visits: visits + 1, // this visit counts too.
+            last_visit: last_visit.map(|time| time.to_local(context.utc_offset).date()),
+            never: &never,
+            note,
+        };
+        let (_, greeting) = context.greetings.greeting_for(
+            self,
+            context.local_time(),
+            &values,
+            context.messages,
+            language,
+        );
+        let mut decision = AdmissionDecision::new(&self.name, &greeting, Outcome::Admitted);
+        decision.language = language.to_string();
+        decision.vouched_for = sponsors::unheard(context.sponsorships, self.id)
+            .cloned()
+            .collect();
+
+        match &self.action {
+            VisitorAction::Accept => {}
+            VisitorAction::AcceptWithNote { note } => {
+                // if the enum option has data, its destructured with {}
+                decision.notes.push(note.clone()); // destructured enum data is available in match scope by name.
+            } // this arm of match uses a scope block instead of a single expression.
+            VisitorAction::Probation => {
+                decision.outcome = Outcome::Probation;
+                // Whoever vouched for them is who to ask about them while they are on probation.
+                decision.sponsor = context
+                    .sponsorships
+                    .iter()
+                    .find(|sponsorship| sponsorship.visitor == self.id)
+                    .map(|sponsorship| sponsorship.sponsor_name.clone());
+            }
+            VisitorAction::Refuse => decision.outcome = Outcome::Refused,
+        }
+        if let (Some(pass), true) = (&self.pass, decision.outcome.lets_in()) {
+            let status = pass.status(self.id, context.now, context.occupancy);
+            if let PassStatus::Lapsed { .. } = status {
+                decision.outcome = Outcome::Lapsed;
+            }
+            decision.pass = Some(status);
+        }
+        if decision.outcome.lets_in() {
+            decision.access = access::evaluate(self, context.groups, context.local_time());
+            if let Some(verdict) = &decision.access {
+                if !verdict.is_allowed() {
+                    decision.outcome = Outcome::Refused;
+                }
+            }
+        }
+        if decision.outcome.lets_in() {
+            decision.minors = context
+                .minors
+                .check(self, context.local_time(), context.occupancy);
+            if decision
+                .minors
+                .iter()
+                .any(|finding| finding.enforcement == Enforcement::Deny)
+            {
+                decision.outcome = Outcome::Refused;
+            }
+        }
+        if decision.outcome.lets_in() {
+            match context.serving.may_serve(self, context.local_time().date()) {
+                Ok(()) => {}
+                Err(ServingRefusal::UnderAge { .. }) => {
+                    decision.warnings.push(Warning::DoNotServeAlcohol)
+                }
+                Err(ServingRefusal::AgeUnknown) => decision.warnings.push(Warning::CheckAge),
+            }
+        }
+        decision
+    }
+}
+
+// enums can derive functionality just like structs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VisitorAction {
+    // like struct declarations, enum declarations don't end with a ;
+    // Accept would be assigned with VisitorAction::Accept
+    Accept, // this is a simple enumeration option with no associated data.
+    //AcceptWithNote would be assigned with VistorAction::AcceptWithNote{note: "my note".to_string()};
+    AcceptWithNote { note: String }, // this enum option contains data.
+    Refuse,
+    Probation,
+}
+
+impl VisitorAction {
+    // The short name used in files and on the command line.
+    pub fn label(&self) -> &'static str {
+        match self {
+            VisitorAction::Accept => "accept",
+            VisitorAction::AcceptWithNote { .. } => "accept_with_note",
+            VisitorAction::Refuse => "refuse",
+            VisitorAction::Probation => "probation",
+        }
+    }
+
+    // The opposite of label. A note is required for accept_with_note and refused for everything else.
+    pub fn from_label(label: &str, note: Option<&str>) -> Result<Self, String> {
+        match (label, note) {
+            ("accept", None) => Ok(VisitorAction::Accept),
+            ("accept_with_note", Some(note)) => Ok(VisitorAction::AcceptWithNote {
+                note: note.to_string(),
+            }),
+            ("accept_with_note", None) => Err("accept_with_note needs a note".to_string()),
+            ("refuse", None) => Ok(VisitorAction::Refuse),
+            ("probation", None) => Ok(VisitorAction::Probation),
+            ("accept" | "refuse" | "probation", Some(_)) => Err(format!(
+                "a note is only allowed with accept_with_note, not {}",
+                label
+            )),
+            (other, _) => Err(format!(
+                "unknown action `{}`, expected accept, accept_with_note, refuse or probation",
+                other
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::Effect;
+    use crate::clock::Timestamp;
+    use crate::config::Config;
+    use crate::messages::Messages;
+    use crate::occupancy::Occupancy;
+
+    fn at(time: &str) -> Timestamp {
+        Timestamp::parse(time).expect("test times are valid")
+    }
+
+    fn born(year: i32) -> Option<Date> {
+        Date::new(year, 1, 1)
+    }
+
+    // Decides for the visitor on a Sunday evening with the default config and nobody inside.
+    fn decide(visitor: &Visitor) -> AdmissionDecision {
+        decide_with(visitor, &Config::default(), at("2026-10-18T19:30:00Z"))
+    }
+
+    fn decide_with(visitor: &Visitor, config: &Config, now: Timestamp) -> AdmissionDecision {
+        let occupancy = Occupancy::default();
+        let messages = Messages::default();
+        visitor.admission_decision(&config.admission_context(now, &occupancy, &messages, &[]))
+    }
+
+    #[test]
+    fn accepted_visitors_are_admitted() {
+        let bert = Visitor::new("Bert", "Hi {name}", VisitorAction::Accept, born(1980));
+        let decision = decide(&bert);
+        assert_eq!(decision.outcome, Outcome::Admitted);
+        assert_eq!(decision.greeting, "Hi Bert");
+        assert!(decision.notes.is_empty());
+        assert!(decision.warnings.is_empty());
+    }
+
+    #[test]
+    fn a_note_is_passed_on() {
+        let action = VisitorAction::AcceptWithNote {
+            note: "Likes cider".to_string(),
+        };
+        let decision = decide(&Visitor::new("Bert", "Hi", action, born(1980)));
+        assert_eq!(decision.outcome, Outcome::Admitted);
+        assert_eq!(decision.notes, ["Likes cider"]);
+        assert!(decision.warnings.is_empty());
+    }
+
+    #[test]
+    fn refused_and_probation_visitors() {
+        let fred = Visitor::new("Fred", "Go away", VisitorAction::Refuse, born(1980));
+        assert_eq!(decide(&fred).outcome, Outcome::Refused);
+        assert!(!decide(&fred).outcome.lets_in());
+
+        let newcomer = Visitor::probationary("Aunt May", born(1960));
+        assert_eq!(decide(&newcomer).outcome, Outcome::Probation);
+        assert!(decide(&newcomer).outcome.lets_in());
+    }
+
+    #[test]
+    fn minors_and_unknown_ages_get_a_serving_warning() {
+        let steve = Visitor::new("Steve", "Hi", VisitorAction::Accept, born(2011));
+        assert_eq!(decide(&steve).warnings, [Warning::DoNotServeAlcohol]);
+
+        let stranger = Visitor::new("Stranger", "Hi", VisitorAction::Accept, None);
+        assert_eq!(decide(&stranger).warnings, [Warning::CheckAge]);
+
+        let fred = Visitor::new("Fred", "Go away", VisitorAction::Refuse, born(2011));
+        assert!(decide(&fred).warnings.is_empty());
+    }
+
+    #[test]
+    fn access_rules_refuse_outside_their_hours() {
+        let mut bert = Visitor::new("Bert", "Hi", VisitorAction::Accept, born(1980));
+        bert.access
+            .push(AccessRule::parse(Effect::Allow, "weekends 10:00-18:00").unwrap());
+        assert_eq!(decide(&bert).outcome, Outcome::Refused);
+
+        let afternoon = at("2026-10-18T14:00:00Z");
+        assert_eq!(
+            decide_with(&bert, &Config::default(), afternoon).outcome,
+            Outcome::Admitted
+        );
+    }
+
+    #[test]
+    fn a_lapsed_pass_is_not_a_refusal() {
+        let mut guest = Visitor::new("Guest", "Hi", VisitorAction::Accept, born(1980));
+        guest.pass = Some(GuestPass {
+            issued_by: 1,
+            valid_from: at("2026-10-01T00:00:00Z"),
+            valid_until: at("2026-10-10T00:00:00Z"),
+            max_uses: None,
+        });
+        assert_eq!(decide(&guest).outcome, Outcome::Lapsed);
+        assert!(!decide(&guest).outcome.lets_in());
+    }
+
+    #[test]
+    fn a_first_visit_is_never_in_the_visitors_language() {
+        let mut ana = Visitor::new(
+            "Ana",
+            "Last time: {last_visit}",
+            VisitorAction::Accept,
+            None,
+        );
+        assert_eq!(decide(&ana).greeting, "Last time: never");
+        ana.language = Some("es".to_string());
+        assert_eq!(decide(&ana).greeting, "Last time: nunca");
+    }
+}